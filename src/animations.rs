@@ -3,11 +3,10 @@ use gloo_console::log;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlElement;
 
-use crate::{Position, line_components::{make_item_id}, Run, Job};
+use crate::{Position, line_components::{make_item_id}, AppState, Job};
 
-pub fn push_subsequent_jobs(pos: &Position, push: bool, runs: &mut [Run]) -> Option<()> {
-    let run = runs.get_mut(pos.run_idx)?;
-    let jobs: &mut Vec<Job> = run.jobs.as_mut();
+pub fn push_subsequent_jobs(pos: &Position, push: bool, state: &mut AppState) -> Option<()> {
+    let jobs: &mut Vec<Job> = state.lane_mut(pos.run_idx)?;
 
     let right_job_idx = match pos.left_job_seq() {
         Some(seq) => seq + 1,
@@ -171,7 +170,7 @@ fn set_class(html_element: &HtmlElement, class_name: &str, set: bool) {
     html_element.set_class_name(&new_class)
 }
 
-fn get_item_at_pos(pos: &Position) -> Option<HtmlElement> {
+pub fn get_item_at_pos(pos: &Position) -> Option<HtmlElement> {
     let item_id = make_item_id(pos);
     if let Some(element) = document().get_element_by_id(&item_id) {
         if let Some(html_element) = element.dyn_ref::<HtmlElement>() {
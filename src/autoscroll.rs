@@ -0,0 +1,65 @@
+// Scrolls the timeline sideways, and the page up and down, while a drag is near their edges.
+// The closer the pointer gets to an edge, the faster it scrolls.
+use std::{cell::Cell, rc::Rc};
+
+use gloo_events::EventListener;
+use gloo_timers::callback::Interval;
+use gloo_utils::window;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, MouseEvent};
+use yew::{NodeRef, html::Scope};
+
+use crate::{App, Msg, pointer::position_at};
+
+// how close to an edge (in px) the pointer has to be to scroll
+const EDGE_PX: f64 = 60.0;
+// the scroll per tick (in px) with the pointer right at an edge
+const MAX_STEP_PX: f64 = 20.0;
+const TICK_MS: u32 = 16;
+
+fn step(pointer: f64, low: f64, high: f64) -> f64 {
+    // negative near `low`, positive near `high`, and zero in between
+    let closeness = |distance: f64| ((EDGE_PX - distance) / EDGE_PX).clamp(0.0, 1.0);
+    MAX_STEP_PX * (closeness(high - pointer) - closeness(pointer - low))
+}
+
+// Scrolls until it is dropped, at the end of the drag.
+pub struct AutoScroll {
+    _interval: Interval,
+    _listeners: [EventListener; 2],
+}
+
+impl AutoScroll {
+    pub fn start(timeline_ref: NodeRef, link: Scope<App>) -> Self {
+        // where the pointer is, from mouse drags and from touch or pen drags
+        let pointer: Rc<Cell<Option<(f64, f64)>>> = Rc::new(Cell::new(None));
+        let track = |event_type| {
+            let pointer = pointer.clone();
+            EventListener::new(&window(), event_type, move |event| {
+                let Some(event) = event.dyn_ref::<MouseEvent>() else { return };
+                pointer.set(Some((event.client_x() as f64, event.client_y() as f64)));
+            })
+        };
+        let listeners = [track("dragover"), track("pointermove")];
+
+        let interval = Interval::new(TICK_MS, move || {
+            let Some((x, y)) = pointer.get() else { return };
+            let Some(timeline) = timeline_ref.cast::<Element>() else { return };
+            let rect = timeline.get_bounding_client_rect();
+            let window_height = window().inner_height().ok().and_then(|height| height.as_f64()).unwrap_or(0.0);
+
+            let dx = step(x, rect.left(), rect.right()).round();
+            let dy = step(y, 0.0, window_height).round();
+            if dx == 0.0 && dy == 0.0 { return };
+            timeline.set_scroll_left(timeline.scroll_left() + dx as i32);
+            window().scroll_by_with_x_and_y(0.0, dy);
+
+            // the pointer is now over something else, so the preview has to follow it
+            if let Some(pos) = position_at(x, y) {
+                link.send_message(Msg::DragEnter(pos));
+            }
+        });
+
+        Self { _interval: interval, _listeners: listeners }
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Job, pairs::PairRole};
+
+// What each position in a Load measures, where it is known.
+pub const DIMENSIONS: [&str; 3] = ["weight", "volume", "pallets"];
+
+// An amount in each dimension, e.g. [weight, volume, pallets].
+// Missing dimensions count as zero.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Load(pub Vec<f32>);
+
+impl Load {
+    fn get(&self, dim: usize) -> f32 {
+        self.0.get(dim).copied().unwrap_or(0.0)
+    }
+
+    fn n_dims(&self, other: &Load) -> usize {
+        self.0.len().max(other.0.len())
+    }
+
+    pub fn add(&self, other: &Load) -> Load {
+        Load((0..self.n_dims(other)).map(|dim| self.get(dim) + other.get(dim)).collect())
+    }
+
+    pub fn sub(&self, other: &Load) -> Load {
+        Load((0..self.n_dims(other)).map(|dim| self.get(dim) - other.get(dim)).collect())
+    }
+
+    pub fn max(&self, other: &Load) -> Load {
+        Load((0..self.n_dims(other)).map(|dim| self.get(dim).max(other.get(dim))).collect())
+    }
+
+    pub fn utilisation(&self, capacity: &Load) -> f32 {
+        // the fullest dimension, as a fraction of capacity
+        (0..self.n_dims(capacity))
+            .filter(|&dim| self.get(dim) > 0.0)
+            .map(|dim| match capacity.get(dim) {
+                cap if cap > 0.0 => self.get(dim) / cap,
+                _ => f32::INFINITY,
+            })
+            .fold(0.0, f32::max)
+    }
+
+    pub fn excess(&self, capacity: &Load) -> f32 {
+        // how much is over capacity, summed over dimensions
+        (0..self.n_dims(capacity))
+            .map(|dim| (self.get(dim) - capacity.get(dim)).max(0.0))
+            .sum()
+    }
+
+    pub fn exceeds(&self, capacity: &Load) -> bool {
+        self.utilisation(capacity) > 1.0
+    }
+
+    pub fn describe(&self) -> String {
+        self.0.iter().enumerate()
+            .map(|(dim, amount)| format!("{} {amount}", DIMENSIONS.get(dim).unwrap_or(&"other")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+pub fn load_profile(jobs: &[Job]) -> Vec<Load> {
+    // The load on board on each leg of a run. Plain jobs' demand is delivered from the depot,
+    // so the run leaves with all of it on board, and what they pick up stays on board to the end.
+    // A paired pickup is loaded at the pickup and unloaded at its delivery.
+    let from_depot = jobs.iter()
+        .filter(|job| job.pair.is_none())
+        .fold(Load::default(), |total, job| total.add(&job.demand));
+
+    let mut on_board = from_depot;
+    let mut profile = vec![on_board.clone()];
+    for job in jobs {
+        on_board = match job.pair.as_ref().map(|pair| pair.role) {
+            Some(PairRole::Pickup) => on_board.add(&job.demand),
+            Some(PairRole::Delivery) => on_board.sub(&job.demand),
+            None => on_board.sub(&job.demand).add(&job.pickup),
+        };
+        profile.push(on_board.clone());
+    }
+    profile
+}
+
+pub fn peak_load(jobs: &[Job]) -> Load {
+    load_profile(jobs).iter().fold(Load::default(), |peak, load| peak.max(load))
+}
+
+pub fn overload(jobs: &[Job], capacity: Option<&Load>) -> f32 {
+    // how far over capacity a run is, summed over its legs
+    let Some(capacity) = capacity else { return 0.0 };
+    load_profile(jobs).iter().map(|load| load.excess(capacity)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{locations::Location, pairs::Pair};
+
+    fn job(uid: &str, demand: &[f32]) -> Job {
+        let mut job = Job::new(uid.to_string(), "#ff0000", Location { lat: 0.0, lon: 0.0, matrix_idx: None }, 0);
+        job.demand = Load(demand.to_vec());
+        job
+    }
+
+    fn paired(mut pickup: Job, mut delivery: Job) -> [Job; 2] {
+        pickup.pair = Some(Pair { role: PairRole::Pickup, partner: delivery.uid.clone() });
+        delivery.pair = Some(Pair { role: PairRole::Delivery, partner: pickup.uid.clone() });
+        [pickup, delivery]
+    }
+
+    #[test]
+    fn plain_jobs_leave_the_depot_on_board() {
+        let jobs = [job("a", &[2.0, 1.0]), job("b", &[3.0])];
+        assert_eq!(load_profile(&jobs), [Load(vec![5.0, 1.0]), Load(vec![3.0, 0.0]), Load(vec![0.0, 0.0])]);
+    }
+
+    #[test]
+    fn pickups_are_carried_to_their_delivery() {
+        let [pickup, delivery] = paired(job("p", &[4.0]), job("d", &[4.0]));
+        let jobs = [pickup, job("a", &[1.0]), delivery];
+        assert_eq!(load_profile(&jobs), [Load(vec![1.0]), Load(vec![5.0]), Load(vec![4.0]), Load(vec![0.0])]);
+        assert_eq!(peak_load(&jobs), Load(vec![5.0]));
+    }
+
+    #[test]
+    fn measures_how_far_over_capacity() {
+        let capacity = Load(vec![4.0, 2.0]);
+        let jobs = [job("a", &[3.0, 1.0]), job("b", &[2.0, 2.0])];
+        // 5 weight and 3 volume leave the depot, then 2 and 2
+        assert_eq!(overload(&jobs, Some(&capacity)), 2.0);
+        assert_eq!(overload(&jobs, None), 0.0);
+        assert!(peak_load(&jobs).exceeds(&capacity));
+        assert_eq!(Load(vec![2.0, 1.0]).utilisation(&capacity), 0.5);
+        assert_eq!(Load(vec![0.0, 0.0, 1.0]).utilisation(&capacity), f32::INFINITY);
+        assert!(!Load(vec![4.0, 2.0]).exceeds(&capacity));
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+
+use yew::prelude::*;
+use web_sys::HtmlSelectElement;
+
+use crate::{Job, Run, Minutes, locations::Location, schedule::{TimeWindow, format_time}, capacity::Load};
+use crate::colors::{get_cycled_color, UNASSIGNED_COLOR};
+
+const DEFAULT_SHIFT_START: Minutes = 8 * 60;
+const DEFAULT_SHIFT_END: Minutes = 17 * 60;
+const DEFAULT_SERVICE_MINUTES: Minutes = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    Uid,
+    Lat,
+    Lon,
+    ServiceMinutes,
+    Earliest,
+    Latest,
+    Weight,
+    Volume,
+    Pallets,
+    Vehicle,
+    ShiftStart,
+    ShiftEnd,
+    DepotLat,
+    DepotLon,
+}
+
+impl Field {
+    pub const ALL: [Field; 14] = [
+        Self::Uid, Self::Lat, Self::Lon, Self::ServiceMinutes, Self::Earliest, Self::Latest,
+        Self::Weight, Self::Volume, Self::Pallets,
+        Self::Vehicle, Self::ShiftStart, Self::ShiftEnd, Self::DepotLat, Self::DepotLon,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Uid => "Job id",
+            Self::Lat => "Latitude",
+            Self::Lon => "Longitude",
+            Self::ServiceMinutes => "Service time",
+            Self::Earliest => "Window start",
+            Self::Latest => "Window end",
+            Self::Weight => "Weight",
+            Self::Volume => "Volume",
+            Self::Pallets => "Pallets",
+            Self::Vehicle => "Vehicle (run)",
+            Self::ShiftStart => "Shift start",
+            Self::ShiftEnd => "Shift end",
+            Self::DepotLat => "Depot latitude",
+            Self::DepotLon => "Depot longitude",
+        }
+    }
+
+    fn required(&self) -> bool {
+        matches!(self, Self::Uid | Self::Lat | Self::Lon)
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        // header names (lowercase, letters and digits only) that probably mean this field
+        match self {
+            Self::Uid => &["uid", "id", "jobid", "job", "order", "orderid"],
+            Self::Lat => &["lat", "latitude", "y"],
+            Self::Lon => &["lon", "lng", "long", "longitude", "x"],
+            Self::ServiceMinutes => &["service", "servicetime", "serviceminutes", "duration"],
+            Self::Earliest => &["earliest", "windowstart", "readytime", "from", "twstart"],
+            Self::Latest => &["latest", "windowend", "duedate", "due", "to", "twend"],
+            Self::Weight => &["weight", "demand", "kg"],
+            Self::Volume => &["volume", "m3"],
+            Self::Pallets => &["pallets"],
+            Self::Vehicle => &["vehicle", "vehicleid", "run", "route", "van", "driver"],
+            Self::ShiftStart => &["shiftstart", "start", "starttime"],
+            Self::ShiftEnd => &["shiftend", "end", "endtime"],
+            Self::DepotLat => &["depotlat", "depotlatitude"],
+            Self::DepotLon => &["depotlon", "depotlng", "depotlongitude"],
+        }
+    }
+}
+
+// Which column (if any) holds each field.
+pub type Mapping = HashMap<Field, usize>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvImport {
+    pub contents: String,
+    pub headers: Vec<String>,
+    pub mapping: Mapping,
+}
+
+pub struct Imported {
+    pub runs: Vec<Run>,
+    pub unassigned: Vec<Job>,
+    pub n_jobs: usize,
+    // one message per row that could not be imported
+    pub errors: Vec<String>,
+}
+
+fn normalise(header: &str) -> String {
+    header.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+fn guess_mapping(headers: &[String]) -> Mapping {
+    let normalised: Vec<String> = headers.iter().map(|header| normalise(header)).collect();
+    Field::ALL.iter()
+        .filter_map(|field| {
+            let column = normalised.iter().position(|header| field.aliases().contains(&header.as_str()))?;
+            Some((*field, column))
+        })
+        .collect()
+}
+
+fn reader(contents: &str) -> csv::Reader<&[u8]> {
+    csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(contents.as_bytes())
+}
+
+pub fn read_headers(contents: String) -> Result<CsvImport, String> {
+    let headers: Vec<String> = reader(&contents).headers()
+        .map_err(|err| format!("Could not read the CSV header row: {err}"))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    if headers.is_empty() {
+        return Err("The CSV file is empty".to_string());
+    }
+    let mapping = guess_mapping(&headers);
+    Ok(CsvImport { contents, headers, mapping })
+}
+
+pub fn parse_time(value: &str) -> Result<Minutes, String> {
+    // "HH:MM" (up to "24:00", the end of the day), or a number of minutes since midnight
+    match value.split_once(':') {
+        Some((hours, minutes)) => {
+            let hours: Minutes = hours.parse().map_err(|_| format!("\"{value}\" is not a time"))?;
+            let minutes: Minutes = minutes.parse().map_err(|_| format!("\"{value}\" is not a time"))?;
+            if minutes >= 60 || hours > 24 || (hours == 24 && minutes > 0) {
+                return Err(format!("\"{value}\" is not a time of day"));
+            }
+            Ok(hours * 60 + minutes)
+        }
+        None => value.parse().map_err(|_| format!("\"{value}\" is not a time")),
+    }
+}
+
+struct Row<'a> {
+    record: &'a csv::StringRecord,
+    mapping: &'a Mapping,
+}
+
+impl Row<'_> {
+    fn get(&self, field: Field) -> Option<&str> {
+        let value = self.record.get(*self.mapping.get(&field)?)?;
+        (!value.is_empty()).then_some(value)
+    }
+
+    fn number<T: std::str::FromStr>(&self, field: Field) -> Result<Option<T>, String> {
+        self.get(field)
+            .map(|value| value.parse().map_err(|_| format!("{} \"{value}\" is not a number", field.label())))
+            .transpose()
+    }
+
+    fn time(&self, field: Field) -> Result<Option<Minutes>, String> {
+        self.get(field)
+            .map(|value| parse_time(value).map_err(|err| format!("{}: {err}", field.label())))
+            .transpose()
+    }
+}
+
+struct ParsedRow {
+    job: Job,
+    vehicle: Option<String>,
+    shift: (Option<Minutes>, Option<Minutes>),
+    depot: Option<Location>,
+}
+
+fn parse_row(row: &Row) -> Result<ParsedRow, String> {
+    let uid = row.get(Field::Uid).ok_or("there is no job id")?.to_string();
+    let lat = row.number(Field::Lat)?.ok_or("there is no latitude")?;
+    let lon = row.number(Field::Lon)?.ok_or("there is no longitude")?;
+    let service_minutes = row.number(Field::ServiceMinutes)?.unwrap_or(DEFAULT_SERVICE_MINUTES);
+
+    let mut job = Job::new(uid, UNASSIGNED_COLOR, Location { lat, lon, matrix_idx: None }, service_minutes);
+    job.window = TimeWindow { earliest: row.time(Field::Earliest)?, latest: row.time(Field::Latest)? };
+    if let TimeWindow { earliest: Some(earliest), latest: Some(latest) } = job.window {
+        if latest < earliest {
+            return Err(format!("the window ends at {} before it starts at {}", format_time(latest), format_time(earliest)));
+        }
+    }
+    let demand = [Field::Weight, Field::Volume, Field::Pallets].iter()
+        .map(|field| row.number(*field).map(Option::unwrap_or_default))
+        .collect::<Result<Vec<f32>, String>>()?;
+    if demand.iter().any(|amount| *amount != 0.0) {
+        job.demand = Load(demand);
+    }
+
+    let depot = match (row.number(Field::DepotLat)?, row.number(Field::DepotLon)?) {
+        (Some(lat), Some(lon)) => Some(Location { lat, lon, matrix_idx: None }),
+        _ => None,
+    };
+
+    Ok(ParsedRow {
+        job,
+        vehicle: row.get(Field::Vehicle).map(str::to_string),
+        shift: (row.time(Field::ShiftStart)?, row.time(Field::ShiftEnd)?),
+        depot,
+    })
+}
+
+pub fn import(csv_import: &CsvImport) -> Result<Imported, String> {
+    // Builds runs (in the order their vehicles first appear) and unassigned jobs from the rows.
+    // Rows which cannot be read, or reuse an earlier row's job id, are skipped, and reported in `errors`.
+    if let Some(missing) = Field::ALL.iter().find(|field| field.required() && !csv_import.mapping.contains_key(field)) {
+        return Err(format!("Choose a column for {}", missing.label()));
+    }
+
+    let mut vehicles: Vec<String> = vec![];
+    let mut runs: HashMap<String, Run> = HashMap::new();
+    let mut unassigned = vec![];
+    let mut errors = vec![];
+    let mut n_jobs = 0;
+    // the row each job id was first used on
+    let mut uid_lines: HashMap<String, usize> = HashMap::new();
+
+    for (idx, record) in reader(&csv_import.contents).records().enumerate() {
+        // the header is line 1
+        let line = idx + 2;
+        let parsed = record
+            .map_err(|err| err.to_string())
+            .and_then(|record| parse_row(&Row { record: &record, mapping: &csv_import.mapping }));
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                errors.push(format!("row {line}: {err}"));
+                continue
+            }
+        };
+        if let Some(first_line) = uid_lines.get(&parsed.job.uid) {
+            errors.push(format!("row {line}: job id \"{}\" is already used on row {first_line}", parsed.job.uid));
+            continue
+        }
+        uid_lines.insert(parsed.job.uid.clone(), line);
+        n_jobs += 1;
+
+        let Some(vehicle) = parsed.vehicle else {
+            unassigned.push(parsed.job);
+            continue
+        };
+        let run = runs.entry(vehicle.clone()).or_insert_with(|| {
+            vehicles.push(vehicle.clone());
+            let depot = parsed.depot.clone().unwrap_or(parsed.job.location.clone());
+            let start_time = parsed.shift.0.unwrap_or(DEFAULT_SHIFT_START);
+            let end_time = parsed.shift.1.unwrap_or(DEFAULT_SHIFT_END).max(start_time);
+            Run::new(vehicle, "", depot, start_time, end_time)
+        });
+        run.jobs.push(parsed.job);
+    }
+
+    let n_runs = vehicles.len();
+    let runs = vehicles.iter().enumerate()
+        .filter_map(|(i, vehicle)| {
+            let mut run = runs.remove(vehicle)?;
+            run.color = get_cycled_color(i, n_runs);
+            for job in run.jobs.iter_mut() {
+                job.color = run.color.clone();
+            }
+            Some(run)
+        })
+        .collect();
+
+    Ok(Imported { runs, unassigned, n_jobs, errors })
+}
+
+#[derive(Properties, PartialEq)]
+pub struct CsvMappingProps {
+    pub headers: Vec<String>,
+    pub mapping: Mapping,
+    pub on_change: Callback<(Field, Option<usize>)>,
+    pub on_import: Callback<MouseEvent>,
+    pub on_cancel: Callback<MouseEvent>,
+}
+
+fn render_field(field: Field, props: &CsvMappingProps) -> Html {
+    let selected = props.mapping.get(&field).copied();
+    let on_change = props.on_change.reform(move |event: Event| {
+        let value = event.target_unchecked_into::<HtmlSelectElement>().value();
+        (field, value.parse().ok())
+    });
+    let label = if field.required() { format!("{} *", field.label()) } else { field.label().to_string() };
+
+    html! {
+        <label class="csv-field">
+            { label }
+            <select onchange={ on_change }>
+                <option value="" selected={ selected.is_none() }>{"(none)"}</option>
+                { for props.headers.iter().enumerate().map(|(column, header)| html! {
+                    <option value={ column.to_string() } selected={ selected == Some(column) }>{ header }</option>
+                })}
+            </select>
+        </label>
+    }
+}
+
+#[function_component(CsvMappingComponent)]
+pub fn csv_mapping(props: &CsvMappingProps) -> Html {
+    html! {
+        <div class="csv-mapping">
+            <div>{"Which column holds each field?"}</div>
+            { for Field::ALL.iter().map(|field| render_field(*field, props)) }
+            <button onclick={ props.on_import.clone() }>{"Import"}</button>
+            <button onclick={ props.on_cancel.clone() }>{"Cancel"}</button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn csv_import(contents: &str) -> CsvImport {
+        read_headers(contents.to_string()).unwrap()
+    }
+
+    #[test]
+    fn guesses_columns_from_header_aliases() {
+        let csv_import = csv_import("Order ID,Latitude,lng,Service Time,Ready Time,Due Date,Van,Notes\n");
+        let mapping = &csv_import.mapping;
+        assert_eq!(mapping.get(&Field::Uid), Some(&0));
+        assert_eq!(mapping.get(&Field::Lat), Some(&1));
+        assert_eq!(mapping.get(&Field::Lon), Some(&2));
+        assert_eq!(mapping.get(&Field::ServiceMinutes), Some(&3));
+        assert_eq!(mapping.get(&Field::Earliest), Some(&4));
+        assert_eq!(mapping.get(&Field::Latest), Some(&5));
+        assert_eq!(mapping.get(&Field::Vehicle), Some(&6));
+        assert_eq!(mapping.len(), 7);
+    }
+
+    #[test]
+    fn parses_rows() {
+        let csv_import = csv_import("id,lat,lon,service,earliest,latest,weight,vehicle,shift start\n\
+            a,51.5,-0.1,15,09:30,630,2.5,van 1,07:00\n\
+            b,51.6,-0.2,,,,,,\n");
+        let imported = import(&csv_import).unwrap();
+        assert_eq!(imported.errors, Vec::<String>::new());
+        assert_eq!(imported.n_jobs, 2);
+
+        let run = &imported.runs[0];
+        assert_eq!((run.vehicle_id.as_str(), run.start_time, run.end_time), ("van 1", 7 * 60, DEFAULT_SHIFT_END));
+        let job = &run.jobs[0];
+        assert_eq!((job.uid.as_str(), job.location.lat, job.location.lon), ("a", 51.5, -0.1));
+        assert_eq!(job.service_minutes, 15);
+        assert_eq!(job.window, TimeWindow { earliest: Some(9 * 60 + 30), latest: Some(630) });
+        assert_eq!(job.demand, Load(vec![2.5, 0.0, 0.0]));
+
+        let job = &imported.unassigned[0];
+        assert_eq!((job.uid.as_str(), job.service_minutes), ("b", DEFAULT_SERVICE_MINUTES));
+        assert_eq!(job.window, TimeWindow::default());
+    }
+
+    #[test]
+    fn reports_bad_rows() {
+        let csv_import = csv_import("id,lat,lon,earliest\n\
+            a,51.5,-0.1,\n\
+            b,north,-0.1,\n\
+            c,51.5,-0.1,noon\n\
+            a,51.6,-0.2,\n");
+        let imported = import(&csv_import).unwrap();
+        assert_eq!(imported.n_jobs, 1);
+        assert_eq!(imported.errors, [
+            "row 3: Latitude \"north\" is not a number",
+            "row 4: Window start: \"noon\" is not a time",
+            "row 5: job id \"a\" is already used on row 2",
+        ]);
+    }
+
+    #[test]
+    fn rejects_impossible_times_and_windows() {
+        assert_eq!(parse_time("24:00"), Ok(24 * 60));
+        assert_eq!(parse_time("25:00"), Err("\"25:00\" is not a time of day".to_string()));
+
+        let csv_import = csv_import("id,lat,lon,earliest,latest\n\
+            a,51.5,-0.1,09:00,09:00\n\
+            b,51.5,-0.1,25:99,\n\
+            c,51.5,-0.1,10:00,09:30\n");
+        let imported = import(&csv_import).unwrap();
+        assert_eq!(imported.n_jobs, 1);
+        assert_eq!(imported.errors, [
+            "row 3: Window start: \"25:99\" is not a time of day",
+            "row 4: the window ends at 09:30 before it starts at 10:00",
+        ]);
+    }
+}
@@ -1,6 +1,10 @@
-use crate::{Position, AppState, ItemIdx, App, Msg};
+use std::collections::HashMap;
+
+use crate::{Position, AppState, RunIdx, Job, App, Msg, schedule::{JobTimes, Violation}, pairs::{Lanes, carry_partner, partner_locked_elsewhere, lane_ok}};
+use crate::optimize::locks_kept;
+use crate::{keyboard::key_move, pointer::uses_pointer_drag};
 use gloo_console::log;
-use web_sys::DragEvent;
+use web_sys::{DragEvent, KeyboardEvent, MouseEvent, PointerEvent};
 use yew::{Callback, html::Scope};
 
 #[derive(Clone, Debug)]
@@ -34,9 +38,49 @@ impl CallbackMgr {
         // self._pos.expect("Cannot create callback without setting pos").clone()
         self._pos.expect("Cannot create callback without setting pos")
     }
-    pub fn drag_start(&self) -> Callback<DragEvent> {
+    pub fn drag_start(&self, locked: bool) -> Callback<DragEvent> {
+        let pos = self.pos();
+        self.link.batch_callback(move |event: DragEvent| {
+            // a locked job stays where it is
+            if locked {
+                event.prevent_default();
+                return vec![];
+            }
+            vec![Msg::DragStart(pos), Msg::StartAutoScroll]
+        })
+    }
+    pub fn pointer_down(&self, locked: bool) -> Callback<PointerEvent> {
         let pos = self.pos();
-        self.link.callback(move |_: DragEvent| Msg::DragStart(pos))
+        self.link.batch_callback(move |event: PointerEvent| {
+            (!locked && uses_pointer_drag(&event)).then(|| Msg::PointerDown(pos, event))
+        })
+    }
+    pub fn toggle_lock(&self) -> Callback<MouseEvent> {
+        let pos = self.pos();
+        self.link.callback(move |event: MouseEvent| {
+            // locking is not selecting
+            event.stop_propagation();
+            Msg::ToggleLock(pos)
+        })
+    }
+    pub fn key_down(&self) -> Callback<KeyboardEvent> {
+        let pos = self.pos();
+        self.link.batch_callback(move |event: KeyboardEvent| {
+            let key = key_move(&event)?;
+            // arrow keys would scroll the timeline
+            event.prevent_default();
+            Some(Msg::KeyMove(pos, key))
+        })
+    }
+    pub fn select(&self) -> Callback<MouseEvent> {
+        let pos = self.pos();
+        self.link.callback(move |event: MouseEvent| {
+            let add = event.shift_key() || event.ctrl_key() || event.meta_key();
+            Msg::Select(pos, add)
+        })
+    }
+    pub fn drag_end(&self) -> Callback<DragEvent> {
+        self.link.callback(|_: DragEvent| Msg::DragEnd)
     }
     pub fn drag_over(&self) -> Callback<DragEvent> {
         let pos = self.pos();
@@ -66,39 +110,190 @@ impl CallbackMgr {
 }
 
 
-pub fn move_job(from_pos: Position, to_pos: Position, state: &mut AppState) {
-    log!(format!("Moving job from {:?} to {:?}", from_pos, to_pos));
-    
-    let runs = &mut state.runs;
-    let from_run = runs.get_mut(from_pos.run_idx).unwrap();
-    
-    let from_job_seq = from_pos.left_job_seq().unwrap();
-    let job = from_run.jobs.remove(from_job_seq);
-    
-    let to_run = if from_pos.run_idx == to_pos.run_idx {
-        log!(format!("Moving to same run"));
-        from_run
-    } else {
-        runs.get_mut(to_pos.run_idx).unwrap()
-    };
-    
-
-    let insert_idx = match to_run.jobs.len() {
-        0 => 0,
-        _ => calc_insertion_idx(&from_pos, &to_pos)
-    };
-    // let insert_idx = calc_insertion_idx(&from_pos, &to_pos);
-    log!(format!("Moving job from_job_seq {:?} insert_seq {:?}", from_job_seq, insert_idx));
-    to_run.jobs.insert(insert_idx, job);
+pub fn move_job(block: &[String], to_pos: Position, state: &mut AppState) -> Result<(), Violation> {
+    // Moves the jobs with uids in `block` to `to_pos`, next to each other in their current order.
+    // Leaves `state` as it was if the move would break up a pickup and delivery, or move a locked job.
+    log!(format!("Moving jobs {:?} to {:?}", block, to_pos));
+    let moved = place_job(block, to_pos, state);
+    if let Err(violation) = moved {
+        log!(format!("Refusing to move jobs {:?}: {}", block, violation.describe()));
+    }
+    moved
+}
+
+// A move tried out on copies of just the lanes it changes, leaving the plan alone.
+struct Trial<'a> {
+    state: &'a AppState,
+    changed: Vec<(RunIdx, Vec<Job>)>,
 }
 
+impl Lanes for Trial<'_> {
+    fn lane(&self, run_idx: RunIdx) -> Option<&Vec<Job>> {
+        match self.changed.iter().find(|(idx, _)| *idx == run_idx) {
+            Some((_, jobs)) => Some(jobs),
+            None => self.state.lane(run_idx),
+        }
+    }
+
+    fn lane_mut(&mut self, run_idx: RunIdx) -> Option<&mut Vec<Job>> {
+        if !self.changed.iter().any(|(idx, _)| *idx == run_idx) {
+            let jobs = self.state.lane(run_idx)?.clone();
+            self.changed.push((run_idx, jobs));
+        }
+        self.changed.iter_mut().find(|(idx, _)| *idx == run_idx).map(|(_, jobs)| jobs)
+    }
+
+    fn find_job(&self, uid: &str) -> Option<(RunIdx, usize)> {
+        self.state.lane_indices().find_map(|run_idx| {
+            let seq = self.lane(run_idx)?.iter().position(|job| job.uid == uid)?;
+            Some((run_idx, seq))
+        })
+    }
+}
+
+pub fn plan_move(block: &[String], to_pos: Position, state: &AppState) -> Result<Vec<(RunIdx, Vec<Job>)>, Violation> {
+    // The new job order of every lane that moving `block` to `to_pos` changes,
+    // or why the move is refused. `state` itself is not changed.
+    let mut trial = Trial { state, changed: vec![] };
+
+    // the block goes after the job to the left of `to_pos`, skipping over jobs in the block itself
+    let Some(to_jobs) = state.lane(to_pos.run_idx) else { return Err(Violation::Precedence) };
+    let anchor = to_pos.left_job_seq()
+        .and_then(|seq| to_jobs.iter().take(seq + 1).rev().find(|job| !block.contains(&job.uid)))
+        .map(|job| job.uid.clone());
 
-fn calc_insertion_idx(from_pos: &Position, to_pos: &Position) -> ItemIdx {
-    let later_in_same_run = (from_pos.run_idx == to_pos.run_idx) 
-                                && (from_pos.item_idx < to_pos.item_idx);
+    let jobs = take_block(block, &mut trial);
+    let to_jobs = trial.lane_mut(to_pos.run_idx).unwrap();
+    let insert_idx = anchor
+        .and_then(|uid| to_jobs.iter().position(|job| job.uid == uid))
+        .map_or(0, |seq| seq + 1);
+    to_jobs.splice(insert_idx..insert_idx, jobs);
+
+    // a pickup or delivery moving to another run takes its partner with it, unless the partner is locked
+    let partner_locked = block.iter().any(|uid| partner_locked_elsewhere(uid, to_pos.run_idx, &trial));
+    for uid in block {
+        carry_partner(uid, to_pos.run_idx, &mut trial);
+    }
+
+    // no job is moved into or out of a locked job's run, ahead of it.
+    // Lanes the move leaves alone were fine before, and still are.
+    let changed = trial.changed;
+    let locks_ok = !partner_locked
+        && changed.iter().all(|(run_idx, jobs)| state.lane(*run_idx).is_some_and(|before| locks_kept(before, jobs)));
+    let pairs_ok = changed.iter().all(|(_, jobs)| lane_ok(jobs, state));
+    match (locks_ok, pairs_ok) {
+        (false, _) => Err(Violation::Locked),
+        (true, false) => Err(Violation::Precedence),
+        (true, true) => Ok(changed),
+    }
+}
+
+pub fn place_job(block: &[String], to_pos: Position, state: &mut AppState) -> Result<(), Violation> {
+    // move_job without the logging. Leaves `state` as it was if the move is refused.
+    for (run_idx, jobs) in plan_move(block, to_pos, state)? {
+        *state.lane_mut(run_idx).unwrap() = jobs;
+    }
+    state.reschedule();
+    Ok(())
+}
+
+fn take_block(block: &[String], lanes: &mut impl Lanes) -> Vec<Job> {
+    // removes the jobs in `block` from wherever they are, in the order of `block`
+    block.iter().filter_map(|uid| {
+        let (run_idx, seq) = lanes.find_job(uid)?;
+        Some(lanes.lane_mut(run_idx)?.remove(seq))
+    }).collect()
+}
+
+pub fn drag_block(pos: Position, selected: &[String], state: &AppState) -> Vec<String> {
+    // The jobs that move when the job at `pos` is dragged: the whole selection if the job
+    // is part of it, in plan order, but never a locked job.
+    let Some(job) = pos.left_job_seq().and_then(|seq| state.lane(pos.run_idx)?.get(seq)) else { return vec![] };
+    if !selected.contains(&job.uid) {
+        return vec![job.uid.clone()];
+    }
+    let mut block: Vec<(RunIdx, usize, String)> = selected.iter()
+        .filter_map(|uid| {
+            let (run_idx, seq) = state.find_job(uid)?;
+            let locked = state.lane(run_idx)?.get(seq)?.locked;
+            (!locked).then(|| (run_idx, seq, uid.clone()))
+        })
+        .collect();
+    block.sort();
+    block.into_iter().map(|(_, _, uid)| uid).collect()
+}
+
+pub fn preview_move(block: &[String], to_pos: Position, state: &mut AppState) -> Option<Violation> {
+    // Schedules a copy of the plan with the dragged jobs dropped at `to_pos`, then stores
+    // the resulting times on each job in `state` so that violations can be shown before the drop.
+    // Returns the first violation (if any) of the dragged jobs themselves.
+    for job in state.all_jobs_mut() {
+        job.preview_times = None;
+    }
+
+    // not move_job, which logs every real drop, as a drag previews many times a second
+    let mut preview = state.clone();
+    if let Err(violation) = place_job(block, to_pos, &mut preview) {
+        return Some(violation);
+    }
+
+    let times: HashMap<&str, JobTimes> = preview.runs.iter()
+        .flat_map(|run| run.jobs.iter())
+        .map(|job| (job.uid.as_str(), job.times))
+        .collect();
+
+    for job in state.all_jobs_mut() {
+        let Some(&preview_times) = times.get(job.uid.as_str()) else { continue };
+        if preview_times != job.times {
+            job.preview_times = Some(preview_times);
+        }
+    }
+
+    // jobs in the pool have no times to check
+    if to_pos.in_pool() { return None };
+    block.iter().find_map(|uid| {
+        let (run_idx, seq) = state.find_job(uid)?;
+        let moved = state.lane(run_idx)?.get(seq)?;
+        moved.window.check(times.get(uid.as_str())?.arrival)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, pairs::{Pair, PairRole}, testing::{block, uids}};
+
+    #[test]
+    fn moves_a_block_in_order() {
+        let mut state = new_state();
+        let (first, second) = (uids(&state, 0)[0].clone(), uids(&state, 0)[1].clone());
+        // after the first job on run 1
+        assert_eq!(place_job(&block(&[&first, &second]), Position::new(1, 2), &mut state), Ok(()));
+        assert_eq!(&uids(&state, 1)[1..3], &[first, second]);
+    }
+
+    #[test]
+    fn keeps_locked_jobs_in_place() {
+        let mut state = new_state();
+        state.runs[1].jobs[0].locked = true;
+        let before = state.clone();
+        let moved = uids(&state, 0)[0].clone();
+        // in front of the locked job
+        assert_eq!(place_job(&block(&[&moved]), Position::new(1, 0), &mut state), Err(Violation::Locked));
+        assert_eq!(state, before);
+        // after it is fine
+        assert_eq!(place_job(&block(&[&moved]), Position::new(1, 2), &mut state), Ok(()));
+    }
 
-    match to_pos.left_job_seq() {
-        Some(seq) => if later_in_same_run { seq } else { seq + 1 },
-        None => 0,
+    #[test]
+    fn does_not_carry_a_locked_partner() {
+        let mut state = new_state();
+        let (pickup, delivery) = (uids(&state, 0)[0].clone(), uids(&state, 0)[1].clone());
+        state.runs[0].jobs[0].pair = Some(Pair { role: PairRole::Pickup, partner: delivery.clone() });
+        state.runs[0].jobs[1].pair = Some(Pair { role: PairRole::Delivery, partner: pickup.clone() });
+        state.runs[0].jobs[1].locked = true;
+        let before = state.clone();
+        assert_eq!(place_job(&block(&[&pickup]), Position::new(1, 0), &mut state), Err(Violation::Locked));
+        assert_eq!(state, before);
     }
 }
@@ -0,0 +1,91 @@
+use gloo_file::{Blob, ObjectUrl};
+use gloo_timers::callback::Timeout;
+use gloo_utils::document;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+use crate::{AppState, schedule::format_time, travel::provider};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportKind {
+    RouteSheet,
+    Json,
+    Vroom,
+}
+
+pub fn route_sheet_csv(state: &AppState) -> Result<String, String> {
+    // One row per stop, ending each run with its return to the end depot.
+    let travel = provider(&state.travel_model, state.travel_matrix.as_deref());
+    let mut writer = csv::Writer::from_writer(vec![]);
+    let write_err = |err: csv::Error| format!("Could not write the route sheet: {err}");
+
+    writer.write_record(["run", "sequence", "uid", "arrival", "departure", "leg_distance"]).map_err(write_err)?;
+    for (run_idx, run) in state.runs.iter().enumerate() {
+        let mut here = &run.start_depot;
+        for (seq, job) in run.jobs.iter().enumerate() {
+            let leg_distance = travel.distance(here, &job.location);
+            writer.write_record([
+                run.label(run_idx),
+                (seq + 1).to_string(),
+                job.uid.clone(),
+                format_time(job.times.arrival),
+                format_time(job.times.departure),
+                format!("{leg_distance:.2}"),
+            ]).map_err(write_err)?;
+            here = &job.location;
+        }
+        let leg_distance = travel.distance(here, &run.end_depot);
+        writer.write_record([
+            run.label(run_idx),
+            (run.jobs.len() + 1).to_string(),
+            "depot".to_string(),
+            format_time(run.return_time),
+            String::new(),
+            format!("{leg_distance:.2}"),
+        ]).map_err(write_err)?;
+    }
+
+    let bytes = writer.into_inner().map_err(|err| format!("Could not write the route sheet: {err}"))?;
+    String::from_utf8(bytes).map_err(|err| err.to_string())
+}
+
+pub fn plan_json(state: &AppState) -> Result<String, String> {
+    serde_json::to_string_pretty(state).map_err(|err| format!("Could not write the plan: {err}"))
+}
+
+pub fn download(filename: &str, mime_type: &str, contents: &str) -> Result<(), String> {
+    // Saves `contents` as a file, by clicking a temporary link to it.
+    let url = ObjectUrl::from(Blob::new_with_options(contents, Some(mime_type)));
+    let link = document().create_element("a").map_err(|_| "Could not start the download")?;
+    link.set_attribute("href", &url).map_err(|_| "Could not start the download")?;
+    link.set_attribute("download", filename).map_err(|_| "Could not start the download")?;
+    link.dyn_into::<HtmlElement>().map_err(|_| "Could not start the download")?.click();
+
+    // the url is revoked when dropped, so give the browser a moment to fetch it first.
+    Timeout::new(1_000, move || drop(url)).forget();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, Job, Run, locations::Location};
+
+    #[test]
+    fn writes_a_route_sheet() {
+        let mut state = new_state();
+        let depot = Location { lat: 0.0, lon: 0.0, matrix_idx: None };
+        let mut run = Run::new(String::new(), "#ff0000", depot.clone(), 8 * 60, 17 * 60);
+        run.jobs = vec![Job::new("a, \"b\"".to_string(), "#ff0000", depot, 0)];
+        state.runs = vec![run];
+        state.reschedule();
+
+        let csv = route_sheet_csv(&state).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines, [
+            "run,sequence,uid,arrival,departure,leg_distance",
+            "run 1,1,\"a, \"\"b\"\"\",08:00,08:00,0.00",
+            "run 1,2,depot,08:00,,0.00",
+        ]);
+    }
+}
@@ -0,0 +1,45 @@
+use gloo_file::{File, callbacks::{FileReader, read_as_text}};
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
+use yew::{Event, html::Scope};
+
+use crate::{App, Msg};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileKind {
+    Matrix,
+    Csv,
+    VroomProblem,
+    VroomSolution,
+    SolomonInstance,
+    SolomonSolution,
+}
+
+impl FileKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Matrix => "matrix",
+            Self::Csv => "CSV",
+            Self::VroomProblem => "VROOM problem",
+            Self::VroomSolution => "VROOM solution",
+            Self::SolomonInstance => "Solomon instance",
+            Self::SolomonSolution => "Solomon solution",
+        }
+    }
+}
+
+pub fn selected_file(event: &Event) -> Option<File> {
+    let input = event.target()?.dyn_into::<HtmlInputElement>().ok()?;
+    let file = input.files()?.get(0)?;
+    // clear the input, so that choosing the same file again still fires onchange
+    input.set_value("");
+    Some(File::from(file))
+}
+
+pub fn read_file(kind: FileKind, file: &File, link: Scope<App>) -> FileReader {
+    // The read is cancelled if the returned FileReader is dropped, so keep hold of it.
+    read_as_text(file, move |result| {
+        let contents = result.map_err(|err| format!("Could not read file: {err}"));
+        link.send_message(Msg::FileRead(kind, contents));
+    })
+}
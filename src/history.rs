@@ -0,0 +1,195 @@
+use gloo_events::EventListener;
+use gloo_utils::window;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, KeyboardEvent};
+use yew::html::Scope;
+
+use crate::{App, AppState, Msg, Position, files::FileKind, travel::TravelModel};
+
+// how many edits can be undone
+const MAX_HISTORY: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Move(Position, Position),
+    Reset,
+    Import(FileKind),
+    SetTravelModel(TravelModel),
+    Optimize,
+    // the uid of the job, and whether it is now locked
+    Lock(String, bool),
+}
+
+impl Command {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Move(_, _) => "move".to_string(),
+            Self::Reset => "reset".to_string(),
+            Self::Import(kind) => format!("{} import", kind.label()),
+            Self::SetTravelModel(model) => format!("{model} travel"),
+            Self::Optimize => "optimize".to_string(),
+            Self::Lock(uid, true) => format!("lock {uid}"),
+            Self::Lock(uid, false) => format!("unlock {uid}"),
+        }
+    }
+}
+
+// Each entry holds a command and the plan to go back to: the plan before the command
+// on the undo stack, and the plan after it on the redo stack.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<(Command, AppState)>,
+    redo: Vec<(Command, AppState)>,
+}
+
+fn restore(state: &mut AppState, snapshot: AppState) {
+    // view settings are not part of the plan, so they are not undone.
+    let px_per_minute = state.px_per_minute;
+    let animation_strategy = state.animation_strategy.clone();
+    *state = snapshot;
+    state.px_per_minute = px_per_minute;
+    state.animation_strategy = animation_strategy;
+}
+
+fn at_rest(mut state: AppState) -> AppState {
+    // drops anything left over from a drag preview
+    for job in state.all_jobs_mut() {
+        job.pushed = false;
+        job.preview_times = None;
+    }
+    state
+}
+
+impl History {
+    pub fn record(&mut self, command: Command, before: AppState) {
+        self.undo.push((command, at_rest(before)));
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    pub fn undo(&mut self, state: &mut AppState) -> Option<Command> {
+        let (command, before) = self.undo.pop()?;
+        self.redo.push((command.clone(), state.clone()));
+        restore(state, before);
+        Some(command)
+    }
+
+    pub fn redo(&mut self, state: &mut AppState) -> Option<Command> {
+        let (command, after) = self.redo.pop()?;
+        self.undo.push((command.clone(), state.clone()));
+        restore(state, after);
+        Some(command)
+    }
+
+    pub fn next_undo(&self) -> Option<&Command> {
+        self.undo.last().map(|(command, _)| command)
+    }
+
+    pub fn next_redo(&self) -> Option<&Command> {
+        self.redo.last().map(|(command, _)| command)
+    }
+}
+
+fn editable(event: &KeyboardEvent) -> bool {
+    // text fields and selects have their own undo
+    let Some(element) = event.target().and_then(|target| target.dyn_into::<HtmlElement>().ok()) else { return false };
+    matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT") || element.is_content_editable()
+}
+
+pub fn listen_for_shortcuts(link: Scope<App>) -> EventListener {
+    // Ctrl+Z undoes, Ctrl+Shift+Z (or Ctrl+Y) redoes. Cmd works in place of Ctrl.
+    EventListener::new(&window(), "keydown", move |event| {
+        let Some(event) = event.dyn_ref::<KeyboardEvent>() else { return };
+        if !(event.ctrl_key() || event.meta_key()) || editable(event) { return };
+
+        let msg = match (event.key().to_lowercase().as_str(), event.shift_key()) {
+            ("z", false) => Msg::Undo,
+            ("z", true) | ("y", false) => Msg::Redo,
+            _ => return,
+        };
+        event.prevent_default();
+        link.send_message(msg);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, animation_strategy::Strategy};
+
+    // a plan told apart from others by the uid of its first job
+    fn plan(uid: &str) -> AppState {
+        let mut state = new_state();
+        state.runs[0].jobs[0].uid = uid.to_string();
+        state
+    }
+
+    fn first_uid(state: &AppState) -> &str {
+        &state.runs[0].jobs[0].uid
+    }
+
+    fn lock(uid: &str) -> Command {
+        Command::Lock(uid.to_string(), true)
+    }
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut history = History::default();
+        let mut state = plan("c");
+        history.record(lock("a"), plan("a"));
+        history.record(lock("b"), plan("b"));
+
+        assert_eq!(history.undo(&mut state), Some(lock("b")));
+        assert_eq!(first_uid(&state), "b");
+        assert_eq!(history.undo(&mut state), Some(lock("a")));
+        assert_eq!(first_uid(&state), "a");
+        assert_eq!(history.undo(&mut state), None);
+
+        assert_eq!(history.redo(&mut state), Some(lock("a")));
+        assert_eq!(first_uid(&state), "b");
+        assert_eq!(history.redo(&mut state), Some(lock("b")));
+        assert_eq!(first_uid(&state), "c");
+        assert_eq!(history.redo(&mut state), None);
+    }
+
+    #[test]
+    fn a_new_edit_clears_redo() {
+        let mut history = History::default();
+        let mut state = plan("b");
+        history.record(lock("a"), plan("a"));
+        history.undo(&mut state);
+        assert_eq!(history.next_redo(), Some(&lock("a")));
+        history.record(lock("c"), plan("c"));
+        assert_eq!(history.next_redo(), None);
+        assert_eq!(history.next_undo(), Some(&lock("c")));
+    }
+
+    #[test]
+    fn keeps_the_latest_edits() {
+        let mut history = History::default();
+        let state = new_state();
+        for i in 0..MAX_HISTORY + 5 {
+            history.record(lock(&i.to_string()), state.clone());
+        }
+        assert_eq!(history.undo.len(), MAX_HISTORY);
+        assert_eq!(history.undo[0].0, lock("5"));
+        assert_eq!(history.next_undo(), Some(&lock(&(MAX_HISTORY + 4).to_string())));
+    }
+
+    #[test]
+    fn undo_keeps_view_settings() {
+        let mut history = History::default();
+        let mut state = plan("b");
+        history.record(lock("a"), plan("a"));
+        state.px_per_minute = 7.0;
+        state.animation_strategy = Strategy::ScaleAndSlide;
+
+        history.undo(&mut state);
+        assert_eq!(first_uid(&state), "a");
+        assert_eq!((state.px_per_minute, state.animation_strategy.clone()), (7.0, Strategy::ScaleAndSlide));
+        history.redo(&mut state);
+        assert_eq!((state.px_per_minute, state.animation_strategy), (7.0, Strategy::ScaleAndSlide));
+    }
+}
@@ -0,0 +1,119 @@
+// Best insertion hints: what dropping the dragged job on each leg would cost,
+// in added travel time and lateness, compared with the plan as it is.
+use crate::{AppState, Position, Run, RunIdx, dnd::plan_move, keyboard::changes_nothing, travel::{TravelTimeProvider, provider}};
+use crate::schedule::{lateness, schedule_run, travel_minutes};
+
+// how many of the cheapest legs get a badge
+pub const RANKED: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Insertion {
+    pub pos: Position,
+    // both in minutes, and both relative to the plan before the drag
+    pub travel: f32,
+    pub lateness: f32,
+    // 1 is the cheapest
+    pub rank: usize,
+}
+
+impl Insertion {
+    pub fn cost(&self) -> f32 {
+        self.travel + self.lateness
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{:+.0} min travel, {:+.0} min late", self.travel, self.lateness)
+    }
+
+    pub fn ranked(&self) -> bool {
+        self.rank <= RANKED
+    }
+}
+
+fn run_cost(run: &Run, travel: &dyn TravelTimeProvider) -> (f32, f32) {
+    (travel_minutes(run, travel), lateness(run))
+}
+
+fn candidate_legs(run_idx: RunIdx, n_jobs: usize) -> impl Iterator<Item = Position> {
+    (0..=n_jobs).map(move |seq| Position::new(run_idx, 2 * seq))
+}
+
+pub fn rank_insertions(block: &[String], state: &AppState) -> Vec<Insertion> {
+    // Tries dropping the dragged jobs on every leg of every run, cheapest first.
+    // Only the runs a drop changes are scheduled again. Legs where the drop would be refused,
+    // or would leave the jobs where they are (around and between them), get no hint.
+    let travel = provider(&state.travel_model, state.travel_matrix.as_deref());
+    let costs_before: Vec<(f32, f32)> = state.runs.iter().map(|run| run_cost(run, travel.as_ref())).collect();
+
+    let mut insertions: Vec<Insertion> = state.runs.iter().enumerate()
+        .flat_map(|(run_idx, run)| candidate_legs(run_idx, run.jobs.len()))
+        .filter_map(|pos| {
+            let changed = plan_move(block, pos, state).ok()?;
+            if changes_nothing(&changed, state) { return None };
+            let (travel_added, lateness_added) = changed.into_iter()
+                // the pool has no cost
+                .filter_map(|(run_idx, jobs)| {
+                    let mut run = Run { jobs, ..state.runs.get(run_idx)?.clone() };
+                    schedule_run(&mut run, travel.as_ref());
+                    let (travel_after, lateness_after) = run_cost(&run, travel.as_ref());
+                    let (travel_before, lateness_before) = costs_before[run_idx];
+                    Some((travel_after - travel_before, lateness_after - lateness_before))
+                })
+                .fold((0.0, 0.0), |(total_travel, total_lateness), (travel, lateness)| {
+                    (total_travel + travel, total_lateness + lateness)
+                });
+            Some(Insertion { pos, travel: travel_added, lateness: lateness_added, rank: 0 })
+        })
+        .collect();
+
+    insertions.sort_by(|a, b| a.cost().total_cmp(&b.cost()));
+    for (i, insertion) in insertions.iter_mut().enumerate() {
+        insertion.rank = i + 1;
+    }
+    insertions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, dnd::place_job, testing::block};
+
+    fn plan_cost(state: &AppState) -> (f32, f32) {
+        let travel = provider(&state.travel_model, state.travel_matrix.as_deref());
+        state.runs.iter().map(|run| run_cost(run, travel.as_ref()))
+            .fold((0.0, 0.0), |(total_travel, total_lateness), (travel, lateness)| (total_travel + travel, total_lateness + lateness))
+    }
+
+    #[test]
+    fn costs_match_the_whole_plan() {
+        let mut state = new_state();
+        state.runs[1].jobs[0].locked = true;
+        let block = vec![state.runs[0].jobs[0].uid.clone()];
+        let (travel_before, lateness_before) = plan_cost(&state);
+
+        let insertions = rank_insertions(&block, &state);
+        // not in front of the locked job
+        assert!(!insertions.iter().any(|insertion| insertion.pos == Position::new(1, 0)));
+        assert!(insertions.iter().any(|insertion| insertion.pos == Position::new(1, 2)));
+        for insertion in insertions {
+            let mut trial = state.clone();
+            place_job(&block, insertion.pos, &mut trial).unwrap();
+            let (travel, lateness) = plan_cost(&trial);
+            assert!((insertion.travel - (travel - travel_before)).abs() < 1e-3);
+            assert!((insertion.lateness - (lateness - lateness_before)).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn no_hints_around_a_block() {
+        // run 1 holds jobs 0 to 3
+        let state = new_state();
+        let legs: Vec<Position> = rank_insertions(&block(&["1", "2"]), &state).iter().map(|insertion| insertion.pos).collect();
+        for item_idx in [2, 4, 6] {
+            assert!(!legs.contains(&Position::new(0, item_idx)));
+        }
+        assert!(legs.contains(&Position::new(0, 0)));
+        assert!(legs.contains(&Position::new(0, 8)));
+        assert!(legs.contains(&Position::new(1, 0)));
+    }
+}
@@ -0,0 +1,189 @@
+// Moving jobs from the keyboard. An arrow key on a focused job picks it up and moves a drop
+// target around, previewed just like a drag. Enter drops it there, Escape puts it back.
+use web_sys::{KeyboardEvent, ScrollIntoViewOptions, ScrollLogicalPosition};
+
+use crate::{AppState, Job, Position, RunIdx, POOL_IDX, animations::get_item_at_pos, dnd::plan_move};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyMove {
+    // earlier or later in the same run
+    Earlier,
+    Later,
+    // the same place on the run above or below
+    Up,
+    Down,
+    Commit,
+    Cancel,
+}
+
+pub fn key_move(event: &KeyboardEvent) -> Option<KeyMove> {
+    match event.key().as_str() {
+        "ArrowLeft" => Some(KeyMove::Earlier),
+        "ArrowRight" => Some(KeyMove::Later),
+        "ArrowUp" => Some(KeyMove::Up),
+        "ArrowDown" => Some(KeyMove::Down),
+        "Enter" => Some(KeyMove::Commit),
+        "Escape" => Some(KeyMove::Cancel),
+        _ => None,
+    }
+}
+
+pub fn first_target(block: &[String], state: &AppState) -> Option<Position> {
+    // the leg just before the first dragged job, where the block already is
+    let (run_idx, seq) = state.find_job(block.first()?)?;
+    Some(Position::new(run_idx, 2 * seq))
+}
+
+fn same_order(before: &[Job], after: &[Job]) -> bool {
+    before.iter().map(|job| &job.uid).eq(after.iter().map(|job| &job.uid))
+}
+
+pub fn changes_nothing(changed: &[(RunIdx, Vec<Job>)], state: &AppState) -> bool {
+    // whether the new job orders of a move are the ones the lanes already have
+    changed.iter().all(|(run_idx, jobs)| state.lane(*run_idx).is_some_and(|before| same_order(before, jobs)))
+}
+
+pub fn no_op(target: Position, block: &[String], state: &AppState) -> bool {
+    // whether dropping the block on `target` would leave every job where it is
+    plan_move(block, target, state).is_ok_and(|changed| changes_nothing(&changed, state))
+}
+
+pub fn next_target(target: Position, key: KeyMove, block: &[String], state: &AppState) -> Position {
+    // The leg the dragged jobs would go to next. Targets are always legs. Of the legs where
+    // dropping would change nothing (around and between the dragged jobs), only the first target is used.
+    let Some(home) = first_target(block, state) else { return target };
+    let skipped = |pos: Position| pos != home && no_op(pos, block, state);
+    let lanes: Vec<usize> = state.lane_indices().collect();
+    let lane_len = |run_idx| state.lane(run_idx).map_or(0, |jobs| jobs.len());
+
+    let target = match key {
+        KeyMove::Earlier | KeyMove::Later => {
+            let later = key == KeyMove::Later;
+            let mut next = target;
+            loop {
+                let item_idx = match later {
+                    true => next.item_idx + 2,
+                    false => match next.item_idx.checked_sub(2) {
+                        Some(item_idx) => item_idx,
+                        None => break target,
+                    },
+                };
+                next = Position::new(next.run_idx, item_idx);
+                // off either end of the run stays put
+                if next.item_idx > 2 * lane_len(next.run_idx) { break target };
+                if !skipped(next) { break next };
+            }
+        }
+        KeyMove::Up | KeyMove::Down => {
+            let Some(lane) = lanes.iter().position(|&run_idx| run_idx == target.run_idx) else { return target };
+            let lane = match key {
+                KeyMove::Up => lane.saturating_sub(1),
+                _ => (lane + 1).min(lanes.len() - 1),
+            };
+            let run_idx = lanes[lane];
+            let seq = (target.item_idx / 2).min(lane_len(run_idx));
+            Position::new(run_idx, 2 * seq)
+        }
+        KeyMove::Commit | KeyMove::Cancel => target,
+    };
+    if skipped(target) { home } else { target }
+}
+
+fn describe_place(uid: &str, state: &AppState) -> Option<String> {
+    let (run_idx, seq) = state.find_job(uid)?;
+    Some(match run_idx {
+        POOL_IDX => format!("the unassigned jobs, position {}", seq + 1),
+        run_idx => format!("run {}, position {}", run_idx + 1, seq + 1),
+    })
+}
+
+pub fn describe_moved(block: &[String], state: &AppState) -> String {
+    // e.g. "Job 3 moved to run 2, position 4"
+    let Some(first) = block.first() else { return String::new() };
+    let place = describe_place(first, state).unwrap_or_default();
+    match block.len() {
+        1 => format!("Job {first} moved to {place}"),
+        _ => format!("Jobs {} moved to {place}", block.join(", ")),
+    }
+}
+
+pub fn describe_kept(block: &[String], state: &AppState) -> String {
+    // e.g. "Job 3 stays at run 2, position 4"
+    let Some(first) = block.first() else { return String::new() };
+    let place = describe_place(first, state).unwrap_or_default();
+    match block.len() {
+        1 => format!("Job {first} stays at {place}"),
+        _ => format!("Jobs {} stay at {place}", block.join(", ")),
+    }
+}
+
+pub fn describe_target(target: Position, block: &[String], state: &AppState) -> String {
+    // where a keyboard move would drop, counting from 1 as if the jobs had already moved:
+    // they go after the jobs left of the target that are not being moved
+    let lane = match target.run_idx {
+        POOL_IDX => "the unassigned jobs".to_string(),
+        run_idx => format!("run {}", run_idx + 1),
+    };
+    let jobs = state.lane(target.run_idx).map_or(&[][..], |jobs| jobs.as_slice());
+    let seq = jobs.iter().take(target.item_idx / 2).filter(|job| !block.contains(&job.uid)).count();
+    format!("{lane}, position {}. Enter to drop, Escape to cancel", seq + 1)
+}
+
+pub fn focus_job(uid: &str, state: &AppState) {
+    let Some((run_idx, seq)) = state.find_job(uid) else { return };
+    if let Some(element) = get_item_at_pos(&Position::new(run_idx, 2 * seq + 1)) {
+        let _ = element.focus();
+    }
+}
+
+pub fn reveal(target: &Position) {
+    // scrolls the timeline and the page just enough to show the target leg
+    let Some(element) = get_item_at_pos(target) else { return };
+    let options = ScrollIntoViewOptions::new();
+    options.set_block(ScrollLogicalPosition::Nearest);
+    options.set_inline(ScrollLogicalPosition::Nearest);
+    element.scroll_into_view_with_scroll_into_view_options(&options);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, testing::block};
+
+    #[test]
+    fn skips_the_leg_after_a_single_job() {
+        // run 1 holds jobs 0 to 3
+        let state = new_state();
+        let block = block(&["1"]);
+        let home = first_target(&block, &state).unwrap();
+        assert_eq!(home, Position::new(0, 2));
+        assert_eq!(next_target(home, KeyMove::Later, &block, &state), Position::new(0, 6));
+        assert_eq!(next_target(Position::new(0, 6), KeyMove::Earlier, &block, &state), home);
+    }
+
+    #[test]
+    fn skips_the_legs_around_a_block() {
+        let state = new_state();
+        let block = block(&["1", "2"]);
+        let home = first_target(&block, &state).unwrap();
+        assert!(no_op(Position::new(0, 4), &block, &state));
+        assert!(no_op(Position::new(0, 6), &block, &state));
+        assert!(!no_op(Position::new(0, 8), &block, &state));
+
+        assert_eq!(next_target(home, KeyMove::Later, &block, &state), Position::new(0, 8));
+        assert_eq!(next_target(Position::new(0, 8), KeyMove::Earlier, &block, &state), home);
+        assert_eq!(next_target(home, KeyMove::Earlier, &block, &state), Position::new(0, 0));
+        // off the end of the run stays put
+        assert_eq!(next_target(Position::new(0, 8), KeyMove::Later, &block, &state), Position::new(0, 8));
+        // the same place on the next run, which the block is not on
+        assert_eq!(next_target(Position::new(0, 8), KeyMove::Down, &block, &state), Position::new(1, 8));
+    }
+
+    #[test]
+    fn counts_positions_without_the_block() {
+        let state = new_state();
+        let block = block(&["1", "2"]);
+        assert_eq!(describe_target(Position::new(0, 8), &block, &state), "run 1, position 3. Enter to drop, Escape to cancel");
+        assert_eq!(describe_target(Position::new(1, 2), &block, &state), "run 2, position 2. Enter to drop, Escape to cancel");
+    }
+}
@@ -1,14 +1,27 @@
 use yew::prelude::*;
-use crate::{Position, RunIdx, Job, locations::driving_time, Minutes, dnd::CallbackMgr, animation_strategy::{Strategy, get_classes}};
+use crate::{Position, RunIdx, POOL_IDX, Job, Minutes, dnd::CallbackMgr, animation_strategy::{Strategy, get_classes}};
+use crate::schedule::{JobTimes, Violation, format_time};
+use crate::colors::UNASSIGNED_COLOR;
+use crate::capacity::{Load, peak_load};
+use crate::load_strip::LoadStripComponent;
+use crate::pairs::Pair;
+use crate::insertion::Insertion;
 
 #[derive(Properties, PartialEq)]
 pub struct JobProps {
     pub pos: Position,
     pub label: String,
     pub color: String,
-    pub duration: f32,
+    pub service_minutes: Minutes,
+    // jobs in the unassigned pool have no times
+    pub times: Option<JobTimes>,
+    pub violation: Option<Violation>,
+    pub pair: Option<Pair>,
+    pub locked: bool,
+    pub selected: bool,
     pub pushed: bool,
     pub pull: bool,
+    pub px_per_minute: f32,
     pub animation_strategy: Strategy,
     pub callback_mgr: CallbackMgr,
 }
@@ -16,23 +29,56 @@ pub struct JobProps {
 pub fn make_item_id(pos: &Position) -> String {
     // creates an id for an item based on its position only
     // so get_element_by_id can be used while dragging.
-    format!("item-run{}-seq{}", pos.run_idx, pos.item_idx)
+    match pos.run_idx {
+        POOL_IDX => format!("item-pool-seq{}", pos.item_idx),
+        run_idx => format!("item-run{}-seq{}", run_idx, pos.item_idx),
+    }
+}
+
+pub fn parse_item_id(id: &str) -> Option<Position> {
+    // the position of an item, from the id made by make_item_id
+    let (lane, item_idx) = id.strip_prefix("item-")?.split_once("-seq")?;
+    let run_idx = match lane {
+        "pool" => POOL_IDX,
+        run => run.strip_prefix("run")?.parse().ok()?,
+    };
+    Some(Position::new(run_idx, item_idx.parse().ok()?))
 }
 
 pub fn make_run_id(run_idx: RunIdx) -> String {
     format!("run{}", run_idx)
 }
 
-fn job_class(push: bool, strategy: Strategy) -> String {
+fn job_class(push: bool, violation: Option<Violation>, strategy: Strategy) -> String {
     let (_, push_approach) = strategy.parts();
     let (push_base, pushed) = push_approach.push_classes();
-    "job".to_owned() + &push_base + if push {&pushed} else {""}
+    let violated = violation.map_or("", |v| v.class());
+    "job".to_owned() + &push_base + if push {&pushed} else {""} + violated
+}
+
+fn with_violation(title: String, violation: Option<Violation>) -> String {
+    match violation {
+        Some(violation) => format!("{title} ({})", violation.describe()),
+        None => title,
+    }
 }
 
 #[function_component(JobComponent)]
 pub fn job(props: &JobProps) -> Html {
-    let style = to_style(vec![&border(&props.color), &width(props.duration)]);
-    let class = job_class(props.pushed, props.animation_strategy.clone());
+    let style = to_style(vec![&border(&props.color), &width(props.service_minutes as f32, props.px_per_minute)]);
+    let class = job_class(props.pushed, props.violation, props.animation_strategy.clone());
+    let class = if props.selected { class + " selected" } else { class };
+    let title = match props.times {
+        Some(times) => with_violation(format!(
+            "arrives {}, starts {}, departs {}",
+            format_time(times.arrival), format_time(times.start), format_time(times.departure),
+        ), props.violation),
+        None => format!("unassigned, {} min on site", props.service_minutes),
+    };
+    let title = match &props.pair {
+        Some(pair) => format!("{}: {title}", pair.describe()),
+        None => title,
+    };
     
     html! {
         <div
@@ -41,16 +87,37 @@ pub fn job(props: &JobProps) -> Html {
             id={ make_item_id(&props.pos) }
             uid={ props.label.clone() }
             class={ class }
-            draggable={ "true" }
-            ondragstart={ &props.callback_mgr.drag_start() }
+            draggable={ (!props.locked).to_string() }
+            ondragstart={ &props.callback_mgr.drag_start(props.locked) }
+            ondragend={ &props.callback_mgr.drag_end() }
+            onpointerdown={ &props.callback_mgr.pointer_down(props.locked) }
             ondragover={ &props.callback_mgr.drag_over() }
             ondragenter={ &props.callback_mgr.drag_enter() }
             ondragleave={ &props.callback_mgr.drag_leave() }
             ondrop={ &props.callback_mgr.drop() }
+            onclick={ props.callback_mgr.select() }
+            onkeydown={ props.callback_mgr.key_down() }
+            tabindex="0"
+            role="button"
+            aria-label={ format!("Job {}", props.label) }
             
             style={ style }
+            title={ title }
         >
             { &props.label }
+            if let Some(pair) = &props.pair {
+                { pair.marker() }
+            }
+            <span
+                class={ if props.locked { "pin locked" } else { "pin" } }
+                title={ if props.locked { "locked, click to unlock" } else { "click to lock" } }
+                onclick={ props.callback_mgr.toggle_lock() }
+            >{"📌"}</span>
+            if let Some(times) = props.times {
+                <span class="job-times">
+                    { format!("{}–{}", format_time(times.arrival), format_time(times.departure)) }
+                </span>
+            }
         </div>
     }
 }
@@ -62,24 +129,39 @@ pub struct LegProps {
     pub duration: f32,
     pub stretched: bool,
     pub pushed: bool,
+    pub violation: Option<Violation>,
+    // what dropping the dragged job here would cost
+    pub insertion: Option<Insertion>,
+    pub hovered: bool,
+    pub push_gap: u32,
+    pub px_per_minute: f32,
     pub animation_strategy: Strategy,
     pub callback_mgr: CallbackMgr,
 }
 
-fn leg_class(stretch: bool, push: bool, strategy: Strategy) -> String {
+fn leg_class(stretch: bool, push: bool, violation: Option<Violation>, strategy: Strategy) -> String {
     let (base, stretched, pushed) = get_classes(strategy);
-    "leg".to_owned() + &base + if stretch {&stretched} else if push {&pushed} else {""}
+    let violated = violation.map_or("", |v| v.class());
+    "leg".to_owned() + &base + if stretch {&stretched} else if push {&pushed} else {""} + violated
 }
 
 #[function_component(LegComponent)]
 pub fn leg(props: &LegProps) -> Html {
     let style = to_style(vec![
-        &width(props.duration), 
-        &leg_scale_vars(props.duration),
+        &width(props.duration, props.px_per_minute),
+        &leg_scale_vars(props.duration, props.px_per_minute, props.push_gap),
     ]);
 
-    let class = leg_class(props.stretched, props.pushed, props.animation_strategy.clone());
-                      
+    let class = leg_class(props.stretched, props.pushed, props.violation, props.animation_strategy.clone());
+    // a violation on a leg describes the dragged job, if it were dropped here.
+    let title = match (props.violation, props.insertion) {
+        (Some(violation), _) => Some(format!("dropping here: {}", violation.describe())),
+        (None, Some(insertion)) => Some(format!("dropping here: {}", insertion.describe())),
+        (None, None) => None,
+    };
+    let badge = props.insertion.filter(|insertion| insertion.ranked());
+    let cost = props.insertion.filter(|_| props.hovered);
+
     html! {
         <div
             // `id` changes when the position changes,
@@ -87,11 +169,19 @@ pub fn leg(props: &LegProps) -> Html {
             id={ make_item_id(&props.pos) }
             class={ class }
             style={ style }
+            title={ title }
             ondragover={ &props.callback_mgr.drag_over() }
             ondragenter={ &props.callback_mgr.drag_enter() }
             ondrop={ &props.callback_mgr.drop() }
             ondragleave={ &props.callback_mgr.drag_leave() }
-        />
+        >
+            if let Some(insertion) = badge {
+                <span class={ format!("insertion-rank rank{}", insertion.rank) }>{ insertion.rank }</span>
+            }
+            if let Some(insertion) = cost {
+                <span class="insertion-cost">{ format!("{:+.0} min", insertion.cost()) }</span>
+            }
+        </div>
     }
 }
 
@@ -105,18 +195,39 @@ fn bg(color: &str) -> String {
     // format!("background-color: transparent")
 }
 
-fn _width(duration: f32) -> u32 {
-    (duration * 25.0).round() as u32
+pub const DEFAULT_PX_PER_MINUTE: f32 = 4.0;
+
+pub fn px(duration: f32, px_per_minute: f32) -> u32 {
+    // all durations are in minutes, and are drawn on the same scale.
+    (duration * px_per_minute).round() as u32
 }
 
-fn width(duration: f32) -> String {
-    let width = _width(duration);
+fn width(duration: f32, px_per_minute: f32) -> String {
+    let width = px(duration, px_per_minute);
     format!("width: {width}px")
 }
 
-fn leg_scale_vars(duration: f32) -> String {
-    let width = _width(duration);
-    let stretch_width = width + 50;
+fn margin_left(duration: f32, px_per_minute: f32) -> String {
+    let margin = px(duration, px_per_minute);
+    format!("margin-left: {margin}px")
+}
+
+// how far a drag pushes later jobs along, at least
+pub const PUSH_GAP_PX: u32 = 50;
+
+pub fn push_gap<'a>(dragged: impl Iterator<Item = &'a Job>, px_per_minute: f32) -> u32 {
+    // room for the dragged jobs, as wide as they are drawn
+    let service_minutes: Minutes = dragged.map(|job| job.service_minutes).sum();
+    px(service_minutes as f32, px_per_minute).max(PUSH_GAP_PX)
+}
+
+fn push_gap_var(push_gap: u32) -> String {
+    format!("--push-gap: {push_gap}px")
+}
+
+fn leg_scale_vars(duration: f32, px_per_minute: f32, push_gap: u32) -> String {
+    let width = px(duration, px_per_minute);
+    let stretch_width = width + push_gap;
     let stretch_ratio = ((stretch_width) as f32) / (width as f32);
     format!("--scale-width: {stretch_width}px; --scale-ratio: {stretch_ratio}")
 }
@@ -132,6 +243,17 @@ pub struct RunProps {
     pub color: String,
     pub start_time: Minutes,
     pub end_time: Minutes,
+    pub return_time: Minutes,
+    pub capacity: Option<Load>,
+    // the time at the left edge of the timeline, which may be earlier than start_time.
+    pub origin: Minutes,
+    pub px_per_minute: f32,
+    pub drop_violation: Option<(Position, Violation)>,
+    pub insertions: Vec<Insertion>,
+    pub dragging_over_pos: Option<Position>,
+    pub selected: Vec<String>,
+    // as wide as all the jobs being dragged
+    pub push_gap: u32,
     pub animation_strategy: Strategy,
     pub callback_mgr: CallbackMgr,
 }
@@ -139,15 +261,27 @@ pub struct RunProps {
 
 fn render_job(pos: Position, job: &Job, run_props: &RunProps) -> Html {
     let callback_mgr = run_props.callback_mgr.with_pos(pos);
+    let times = job.preview_times.unwrap_or(job.times);
+    // hovering over a job drops the dragged job next to it
+    let violation = run_props.drop_violation
+        .filter(|(violation_pos, _)| *violation_pos == pos)
+        .map(|(_, violation)| violation)
+        .or(job.window.check(times.arrival));
 
     html! {
         <JobComponent
             pos={ pos }
             label={ job.uid.clone() }
             color={ job.color.clone() }
-            duration={ 1.0 }
+            service_minutes={ job.service_minutes }
+            times={ Some(times) }
+            violation={ violation }
+            pair={ job.pair.clone() }
+            locked={ job.locked }
+            selected={ run_props.selected.contains(&job.uid) }
             pushed={ job.pushed }
             pull={ job.pull }
+            px_per_minute={ run_props.px_per_minute }
             animation_strategy={ run_props.animation_strategy.clone() }
             callback_mgr={ callback_mgr }
         />
@@ -157,6 +291,10 @@ fn render_job(pos: Position, job: &Job, run_props: &RunProps) -> Html {
 
 fn render_leg(pos: Position, duration: f32, stretched: bool, pushed: bool, run_props: &RunProps) -> Html {
     let callback_mgr = run_props.callback_mgr.with_pos(pos);
+    let violation = run_props.drop_violation
+        .filter(|(violation_pos, _)| *violation_pos == pos)
+        .map(|(_, violation)| violation);
+    let insertion = run_props.insertions.iter().find(|insertion| insertion.pos == pos).copied();
 
     html! {
         <LegComponent
@@ -165,6 +303,11 @@ fn render_leg(pos: Position, duration: f32, stretched: bool, pushed: bool, run_p
             duration={ duration }
             stretched={ stretched }
             pushed={ pushed }
+            violation={ violation }
+            insertion={ insertion }
+            hovered={ run_props.dragging_over_pos == Some(pos) }
+            push_gap={ run_props.push_gap }
+            px_per_minute={ run_props.px_per_minute }
             animation_strategy={ run_props.animation_strategy.clone() }
             callback_mgr={ callback_mgr }
         />
@@ -177,8 +320,12 @@ fn construct_run_elements(run_props: &RunProps) -> Vec<yew::virtual_dom::VNode>
     
     let mut item_idx = 0;
     let pos = Position { run_idx, item_idx };
-    let stretched = run_props.jobs.first().map_or(false, |job| job.pushed);
-    let first_leg = render_leg(pos, 5.0, stretched, false, run_props);
+    let stretched = run_props.jobs.first().is_some_and(|job| job.pushed);
+    // legs span from one job's departure to the next job's start, so they include any waiting.
+    // a run with no jobs goes straight from its start depot to its end depot.
+    let first_leg_end = run_props.jobs.first().map_or(run_props.return_time, |job| job.times.start);
+    let first_leg_duration = (first_leg_end - run_props.start_time) as f32;
+    let first_leg = render_leg(pos, first_leg_duration, stretched, false, run_props);
     items.push(first_leg);
 
     let mut prev_job = None;
@@ -195,7 +342,7 @@ fn construct_run_elements(run_props: &RunProps) -> Vec<yew::virtual_dom::VNode>
             }
         };
 
-        let leg_duration = driving_time(prev, job);
+        let leg_duration = (job.times.start - prev.times.departure) as f32;
         
         item_idx += 1;
         let pos = Position { run_idx, item_idx };
@@ -210,8 +357,10 @@ fn construct_run_elements(run_props: &RunProps) -> Vec<yew::virtual_dom::VNode>
 
     item_idx += 1;
     let pos = Position { run_idx, item_idx };
-    let pushed = prev_job.map_or(false, |job| job.pushed);
-    let last_leg = render_leg(pos, 5.0, false, pushed, run_props);
+    let pushed = prev_job.is_some_and(|job| job.pushed);
+    let leave_last = prev_job.map_or(run_props.return_time, |job| job.times.departure);
+    let last_leg_duration = (run_props.return_time - leave_last) as f32;
+    let last_leg = render_leg(pos, last_leg_duration, false, pushed, run_props);
     items.push(last_leg);
     items
 }    
@@ -219,11 +368,106 @@ fn construct_run_elements(run_props: &RunProps) -> Vec<yew::virtual_dom::VNode>
 
 #[function_component(RunComponent)]
 pub fn run(props: &RunProps) -> Html {
-    let day_length = props.end_time - props.start_time;
-    let style = to_style(vec![&bg(&props.color), &width(day_length as f32)]);
+    let shift_end = props.end_time.max(props.return_time);
+    let day_length = shift_end - props.start_time;
+    let style = to_style(vec![
+        &bg(&props.color),
+        &width(day_length as f32, props.px_per_minute),
+        &margin_left((props.start_time - props.origin) as f32, props.px_per_minute),
+        &push_gap_var(props.push_gap),
+    ]);
+
+    let peak = peak_load(&props.jobs);
+    let overloaded = props.capacity.as_ref().is_some_and(|capacity| peak.exceeds(capacity));
+    let class = if overloaded { "run overloaded" } else { "run" };
+    let title = props.capacity.as_ref()
+        .filter(|_| overloaded)
+        .map(|capacity| format!("over capacity: carrying {} of {}", peak.describe(), capacity.describe()));
+
     html! {
-        <div class="run" id={ make_run_id(props.run_idx) } style={ style }>
-            { for construct_run_elements(props) }
+        <>
+            <div class={ class } id={ make_run_id(props.run_idx) } style={ style } title={ title }>
+                { for construct_run_elements(props) }
+            </div>
+            <LoadStripComponent
+                jobs={ props.jobs.clone() }
+                capacity={ props.capacity.clone() }
+                start_time={ props.start_time }
+                return_time={ props.return_time }
+                origin={ props.origin }
+                px_per_minute={ props.px_per_minute }
+            />
+        </>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PoolProps {
+    pub jobs: Vec<Job>,
+    pub selected: Vec<String>,
+    pub px_per_minute: f32,
+    pub animation_strategy: Strategy,
+    pub callback_mgr: CallbackMgr,
+}
+
+// the drop zones between unassigned jobs
+const POOL_GAP_MINUTES: f32 = 5.0;
+
+fn render_pool_leg(pos: Position, pool_props: &PoolProps) -> Html {
+    html! {
+        <LegComponent
+            pos={ pos }
+            color={ UNASSIGNED_COLOR }
+            duration={ POOL_GAP_MINUTES }
+            stretched={ false }
+            pushed={ false }
+            violation={ None }
+            insertion={ None }
+            hovered={ false }
+            push_gap={ PUSH_GAP_PX }
+            px_per_minute={ pool_props.px_per_minute }
+            animation_strategy={ pool_props.animation_strategy.clone() }
+            callback_mgr={ pool_props.callback_mgr.with_pos(pos) }
+        />
+    }
+}
+
+fn render_pool_job(pos: Position, job: &Job, pool_props: &PoolProps) -> Html {
+    html! {
+        <JobComponent
+            pos={ pos }
+            label={ job.uid.clone() }
+            color={ job.color.clone() }
+            service_minutes={ job.service_minutes }
+            times={ None }
+            violation={ None }
+            pair={ job.pair.clone() }
+            locked={ job.locked }
+            selected={ pool_props.selected.contains(&job.uid) }
+            pushed={ job.pushed }
+            pull={ job.pull }
+            px_per_minute={ pool_props.px_per_minute }
+            animation_strategy={ pool_props.animation_strategy.clone() }
+            callback_mgr={ pool_props.callback_mgr.with_pos(pos) }
+        />
+    }
+}
+
+#[function_component(PoolComponent)]
+pub fn pool(props: &PoolProps) -> Html {
+    // laid out like a run (leg, job, leg, ..., job, leg) so that Positions work the same way.
+    let mut items = vec![render_pool_leg(Position::new(POOL_IDX, 0), props)];
+    for (seq, job) in props.jobs.iter().enumerate() {
+        items.push(render_pool_job(Position::new(POOL_IDX, 2 * seq + 1), job, props));
+        items.push(render_pool_leg(Position::new(POOL_IDX, 2 * seq + 2), props));
+    }
+
+    html! {
+        <div class="pool">
+            <div class="pool-title">{ format!("Unassigned ({})", props.jobs.len()) }</div>
+            <div class="pool-jobs">
+                { for items }
+            </div>
         </div>
     }
-}
\ No newline at end of file
+}
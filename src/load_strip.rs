@@ -0,0 +1,61 @@
+use yew::prelude::*;
+
+use crate::{Job, Minutes, capacity::{Load, load_profile}, line_components::px};
+
+#[derive(Properties, PartialEq)]
+pub struct LoadStripProps {
+    pub jobs: Vec<Job>,
+    pub capacity: Option<Load>,
+    pub start_time: Minutes,
+    pub return_time: Minutes,
+    pub origin: Minutes,
+    pub px_per_minute: f32,
+}
+
+fn utilisation_class(utilisation: f32) -> &'static str {
+    match utilisation {
+        u if u > 1.0 => "load-segment over",
+        u if u > 0.8 => "load-segment near",
+        _ => "load-segment",
+    }
+}
+
+fn render_segment(from: Minutes, to: Minutes, load: &Load, props: &LoadStripProps) -> Html {
+    let width = px(to.saturating_sub(from) as f32, props.px_per_minute);
+    let (utilisation, title) = match &props.capacity {
+        Some(capacity) => (
+            load.utilisation(capacity),
+            format!("on board: {} (capacity {})", load.describe(), capacity.describe()),
+        ),
+        None => (0.0, format!("on board: {}", load.describe())),
+    };
+    let fill = (utilisation.min(1.0) * 100.0).round();
+
+    html! {
+        <div class={ utilisation_class(utilisation) } style={ format!("width: {width}px") } title={ title }>
+            <div class="load-fill" style={ format!("height: {fill}%") } />
+        </div>
+    }
+}
+
+#[function_component(LoadStripComponent)]
+pub fn load_strip(props: &LoadStripProps) -> Html {
+    // The load changes when each job is done, so each segment runs up to a job's departure.
+    let profile = load_profile(&props.jobs);
+    let mut from = props.start_time;
+    let mut segments = vec![];
+    for (job, load) in props.jobs.iter().zip(profile.iter()) {
+        segments.push(render_segment(from, job.times.departure, load, props));
+        from = job.times.departure;
+    }
+    if let Some(load) = profile.last() {
+        segments.push(render_segment(from, props.return_time, load, props));
+    }
+
+    let margin = px((props.start_time - props.origin) as f32, props.px_per_minute);
+    html! {
+        <div class="load-strip" style={ format!("margin-left: {margin}px") }>
+            { for segments }
+        </div>
+    }
+}
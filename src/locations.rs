@@ -1,18 +1,23 @@
 use serde::{Deserialize, Serialize};
 
-use crate::Job;
-
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Location {
     pub lat: f32,
     pub lon: f32,
+    // row/column of this location in a precomputed travel matrix
+    #[serde(default)]
+    pub matrix_idx: Option<usize>,
 }
 
+// the demo plan's stops are scattered over a square this many degrees across, about the size of a town
+const RANDOM_SPREAD: f32 = 0.1;
+
 impl Location {
     pub fn new_random() -> Self {
         Self {
-           lat: rand::random(),
-           lon: rand::random(),
+           lat: rand::random::<f32>() * RANDOM_SPREAD,
+           lon: rand::random::<f32>() * RANDOM_SPREAD,
+           matrix_idx: None,
         }
     }
 }
@@ -21,7 +26,7 @@ const R: f32 = 6371.0;
 
 pub fn haversine_dist(loc1: &Location, loc2: &Location) -> f32 {
     let d_lat = (loc2.lat - loc1.lat).to_radians();
-    let d_lon = (loc2.lon - loc2.lat).to_radians();
+    let d_lon = (loc2.lon - loc1.lon).to_radians();
 
     let lat1 = loc1.lat.to_radians();
     let lat2 = loc2.lat.to_radians();
@@ -31,7 +36,3 @@ pub fn haversine_dist(loc1: &Location, loc2: &Location) -> f32 {
 
     R * c
 }
-
-pub fn driving_time(job1: &Job, job2: &Job) -> f32 {
-    haversine_dist(&job1.location, &job2.location) / 25.0
-}
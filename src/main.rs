@@ -1,39 +1,97 @@
 
-use std::str::FromStr;
+use std::{collections::HashMap, rc::Rc, str::FromStr};
 
 use animations::{toggle_visible, push_subsequent_jobs};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use strum::VariantNames;
 use wasm_bindgen::JsCast;
 use yew::{prelude::*};
 
 
-use gloo_storage::{Storage, LocalStorage};
-use web_sys::{HtmlInputElement};
+use web_sys::{HtmlInputElement, HtmlSelectElement, PointerEvent};
 
 mod line_components;
-use crate::{line_components::RunComponent, dnd::CallbackMgr, animation_strategy::Strategy};
+use crate::{line_components::{RunComponent, PoolComponent, DEFAULT_PX_PER_MINUTE, push_gap}, dnd::CallbackMgr, animation_strategy::Strategy};
 
 mod colors;
-use colors::get_color;
+use colors::{get_color, UNASSIGNED_COLOR};
 
 mod locations;
 use locations::Location;
 
 mod dnd;
-use dnd::move_job;
+use dnd::{move_job, preview_move, drag_block};
 
 mod animations;
 mod animation_strategy;
 
+mod schedule;
+mod zoom;
+mod ruler;
+use ruler::RulerComponent;
+use schedule::{JobTimes, TimeWindow, Violation, schedule_runs};
+
+mod travel;
+use travel::{TravelModel, TravelMatrix, provider};
+
+mod capacity;
+use capacity::Load;
+mod load_strip;
+
+mod pairs;
+use pairs::{Pair, PairRole};
+
+mod storage;
+mod plans;
+use plans::{PlanIndex, PlanAction};
+
+mod history;
+use history::{History, Command, listen_for_shortcuts};
+use gloo_events::EventListener;
+use gloo_utils::window;
+
+mod csv_import;
+use csv_import::{CsvImport, CsvMappingComponent, Field};
+
+mod export;
+use export::ExportKind;
+
+mod matrix;
+mod files;
+mod insertion;
+mod keyboard;
+mod pointer;
+mod autoscroll;
+use autoscroll::AutoScroll;
+use pointer::PointerDrag;
+use keyboard::KeyMove;
+mod optimize;
+use optimize::{Proposal, OptimizeDiffComponent};
+use insertion::{Insertion, rank_insertions};
+mod vroom;
+mod solomon;
+use files::{FileKind, selected_file, read_file};
+use gloo_file::{File, callbacks::FileReader};
+
 type Minutes = usize;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Run {
+    #[serde(default)]
+    pub vehicle_id: String,
     pub jobs: Vec<Job>,
     pub color: String,
+    pub start_depot: Location,
+    pub end_depot: Location,
     pub start_time: Minutes,
     pub end_time: Minutes,
+    // None if the vehicle can carry anything
+    #[serde(default)]
+    pub capacity: Option<Load>,
+    // when the run gets back to its end depot, worked out by the schedule
+    #[serde(default)]
+    pub return_time: Minutes,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -41,20 +99,156 @@ pub struct Job {
     pub uid: String,
     pub color: String,
     pub location: Location,
+    pub service_minutes: Minutes,
+    #[serde(default)]
+    pub window: TimeWindow,
+    #[serde(default)]
+    pub demand: Load,
+    // what a plain job collects, to be carried back to the end depot
+    #[serde(default)]
+    pub pickup: Load,
+    #[serde(default)]
+    pub pair: Option<Pair>,
+    // a locked job can't be dragged, and the optimizer leaves it where it is
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub times: JobTimes,
+    // times this job would have if the job being dragged were dropped where it is hovering
+    #[serde(skip)]
+    pub preview_times: Option<JobTimes>,
     pub pushed: bool,
     pub pull: bool,
 }
 
+impl Job {
+    pub fn new(uid: String, color: &str, location: Location, service_minutes: Minutes) -> Self {
+        Self {
+            uid,
+            color: color.to_string(),
+            location,
+            service_minutes,
+            window: TimeWindow::default(),
+            demand: Load::default(),
+            pickup: Load::default(),
+            pair: None,
+            locked: false,
+            times: JobTimes::default(),
+            preview_times: None,
+            pushed: false,
+            pull: false,
+        }
+    }
+}
+
+impl Run {
+    pub fn new(vehicle_id: String, color: &str, depot: Location, start_time: Minutes, end_time: Minutes) -> Self {
+        // starts and ends at the same depot, with no jobs yet
+        Self {
+            vehicle_id,
+            jobs: vec![],
+            color: color.to_string(),
+            start_depot: depot.clone(),
+            end_depot: depot,
+            start_time,
+            end_time,
+            capacity: None,
+            return_time: start_time,
+        }
+    }
+
+    pub fn label(&self, run_idx: RunIdx) -> String {
+        // the vehicle, or where the run is if it has no vehicle id (e.g. saved before there were ids)
+        match self.vehicle_id.is_empty() {
+            true => format!("run {}", run_idx + 1),
+            false => self.vehicle_id.clone(),
+        }
+    }
+}
+
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct AppState {
     pub runs: Vec<Run>,
+    // jobs which are not on any run yet
+    #[serde(default)]
+    pub unassigned: Vec<Job>,
     pub animation_strategy: Strategy,
+    #[serde(default = "default_px_per_minute")]
+    pub px_per_minute: f32,
+    #[serde(default)]
+    pub travel_model: TravelModel,
+    #[serde(default)]
+    // shared, so that copies of the plan (for undo, or previews) do not copy the matrix
+    pub travel_matrix: Option<Rc<TravelMatrix>>,
+}
+
+impl AppState {
+    pub fn reschedule(&mut self) {
+        let travel = provider(&self.travel_model, self.travel_matrix.as_deref());
+        schedule_runs(&mut self.runs, travel.as_ref());
+    }
+
+    pub fn lane(&self, run_idx: RunIdx) -> Option<&Vec<Job>> {
+        match run_idx {
+            POOL_IDX => Some(&self.unassigned),
+            _ => self.runs.get(run_idx).map(|run| &run.jobs),
+        }
+    }
+
+    pub fn lane_mut(&mut self, run_idx: RunIdx) -> Option<&mut Vec<Job>> {
+        match run_idx {
+            POOL_IDX => Some(&mut self.unassigned),
+            _ => self.runs.get_mut(run_idx).map(|run| &mut run.jobs),
+        }
+    }
+
+    pub fn lane_indices(&self) -> impl Iterator<Item = RunIdx> {
+        (0..self.runs.len()).chain([POOL_IDX])
+    }
+
+    pub fn find_job(&self, uid: &str) -> Option<(RunIdx, usize)> {
+        // the lane and sequence of the job with this uid
+        self.lane_indices().find_map(|run_idx| {
+            let seq = self.lane(run_idx)?.iter().position(|job| job.uid == uid)?;
+            Some((run_idx, seq))
+        })
+    }
+
+    pub fn all_jobs_mut(&mut self) -> impl Iterator<Item = &mut Job> {
+        self.runs.iter_mut().flat_map(|run| run.jobs.iter_mut()).chain(self.unassigned.iter_mut())
+    }
+
+    pub fn take_jobs(&mut self) -> HashMap<String, Job> {
+        // empties every run and the pool, so that imported routes can be laid out again
+        let runs = self.runs.iter_mut().map(|run| &mut run.jobs);
+        runs.chain([&mut self.unassigned])
+            .flat_map(|jobs| jobs.drain(..))
+            .map(|job| (job.uid.clone(), job))
+            .collect()
+    }
+
+    pub fn unassign(&mut self, jobs: HashMap<String, Job>) {
+        // puts jobs in the pool in uid order, numbers sorting by value
+        let mut jobs: Vec<Job> = jobs.into_values().collect();
+        jobs.sort_by(|a, b| (a.uid.len(), &a.uid).cmp(&(b.uid.len(), &b.uid)));
+        for job in jobs.iter_mut() {
+            job.color = UNASSIGNED_COLOR.to_string();
+        }
+        self.unassigned.extend(jobs);
+    }
+}
+
+fn default_px_per_minute() -> f32 {
+    DEFAULT_PX_PER_MINUTE
 }
 
 type RunIdx = usize;
 type ItemIdx = usize;
 
+// The unassigned pool is laid out (and addressed) like a run, at this index.
+pub const POOL_IDX: RunIdx = RunIdx::MAX;
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub struct Position {
     pub run_idx: RunIdx,
@@ -75,7 +269,11 @@ impl Position {
     }
 
     pub fn is_leg(&self) -> bool {
-        self.item_idx % 2 == 0
+        self.item_idx.is_multiple_of(2)
+    }
+
+    pub fn in_pool(&self) -> bool {
+        self.run_idx == POOL_IDX
     }
 }
 
@@ -85,116 +283,605 @@ pub enum Msg {
     DragOver(Position),
     DragEnter(Position),
     DragLeave(Position),
+    DragEnd,
+    // mouse and touch drags scroll near the edges, keyboard moves scroll their target into view
+    StartAutoScroll,
+    // shift or ctrl adds the job to the selection, instead of selecting only it
+    Select(Position, bool),
+    ToggleLock(Position),
+    KeyMove(Position, KeyMove),
+    // a touch or pen drag, which ends with PointerDone
+    PointerDown(Position, PointerEvent),
+    PointerDone,
     // ToggleAnimations,
     SetAnimationStrategy(Strategy),
+    SetTravelModel(TravelModel),
+    // zoom by a factor, keeping the time at the given pointer offset (or the centre) in place
+    Zoom(f32, Option<f64>),
+    Pan(f32),
+    Undo,
+    Redo,
+    Plan(PlanAction),
+    MapCsvColumn(Field, Option<usize>),
+    ImportCsv,
+    CancelCsv,
+    Export(ExportKind),
+    // None optimizes all runs together
+    Optimize(Option<RunIdx>),
+    ApplyOptimized,
+    DiscardOptimized,
+    ReadFile(FileKind, File),
+    FileRead(FileKind, Result<String, String>),
     Reset,
 }
 
-fn new_jobs(color: &str) -> Vec<Job> {
+fn new_window(start_time: Minutes) -> TimeWindow {
+    // customers book 2 hour slots, starting on the half hour.
+    let earliest = start_time + 30 * rand::thread_rng().gen_range(0..=2);
+    TimeWindow::new(earliest, earliest + 2 * 60)
+}
+
+fn new_demand() -> Load {
+    let mut rng = rand::thread_rng();
+    // weight, volume, pallets
+    Load(vec![rng.gen_range(5..=40) as f32, rng.gen_range(1..=3) as f32, rng.gen_range(0..=1) as f32])
+}
+
+fn new_jobs(first_uid: usize, color: &str, start_time: Minutes) -> Vec<Job> {
     let n = 4;
     (0..n)
-        .map(|n| Job{
-            uid: format!("{}", n),
-            color: color.to_string(),
-            location: Location::new_random(),
-            pushed: false,
-            pull: false,
+        .map(|n| {
+            // uids are unique across all runs
+            let uid = format!("{}", first_uid + n);
+            let service_minutes = rand::thread_rng().gen_range(5..=30);
+            let mut job = Job::new(uid, color, Location::new_random(), service_minutes);
+            if n % 2 == 0 { job.window = new_window(start_time) };
+            job.demand = new_demand();
+            job
         }).collect()
 }
 
+fn new_unassigned(first_uid: usize) -> Vec<Job> {
+    let mut jobs = new_jobs(first_uid, UNASSIGNED_COLOR, 8 * 60);
+    jobs.truncate(3);
+    // the first two are a pickup and its delivery
+    jobs[1].demand = jobs[0].demand.clone();
+    jobs[0].pair = Some(Pair { role: PairRole::Pickup, partner: jobs[1].uid.clone() });
+    jobs[1].pair = Some(Pair { role: PairRole::Delivery, partner: jobs[0].uid.clone() });
+    jobs
+}
+
 fn new_runs() -> Vec<Run> {
     let n = 3;
     (0..n).map(|i| {
-        let jobs = new_jobs(&get_color(i, n));
-        Run { jobs, color: get_color(i, n), start_time: 0, end_time: 3 * 60 }}
-    ).collect()
+        let start_time = 8 * 60;
+        let color = get_color(i, n);
+        let mut run = Run::new(format!("van {}", i + 1), &color, Location::new_random(), start_time, 11 * 60);
+        run.jobs = new_jobs(4 * i, &color, start_time);
+        run.capacity = Some(Load(vec![100.0, 10.0, 4.0]));
+        run
+    }).collect()
+}
+
+fn new_state() -> AppState {
+    let mut state = AppState {
+        runs: new_runs(),
+        unassigned: new_unassigned(12),
+        animation_strategy: Strategy::None,
+        px_per_minute: DEFAULT_PX_PER_MINUTE,
+        travel_model: TravelModel::default(),
+        travel_matrix: None,
+    };
+    state.reschedule();
+    state
+}
+
+fn load_plan(plans: &PlanIndex) -> (AppState, Option<String>) {
+    // the open plan, or a new one if it has not been saved (or cannot be loaded)
+    let (saved, status) = match storage::load(&plans.current_key()) {
+        Ok(saved) => (saved, None),
+        Err(message) => (None, Some(message)),
+    };
+    let mut state = saved.unwrap_or_else(new_state);
+    state.reschedule();
+    (state, status)
 }
 
 pub struct App {
     state: AppState,
+    plans: PlanIndex,
+    plan_name_ref: NodeRef,
     drag_from_pos: Option<Position>,
+    // the uids of the jobs being dragged, and of the jobs selected to be dragged together
+    dragged: Vec<String>,
+    selected: Vec<String>,
+    // where a keyboard move would drop the dragged jobs, while one is going on
+    key_target: Option<Position>,
+    // read out by screen readers
+    announcement: String,
+    pending_focus: Option<String>,
+    pending_reveal: Option<Position>,
+    pointer_drag: Option<PointerDrag>,
+    auto_scroll: Option<AutoScroll>,
     dragging_over_pos: Option<Position>,
+    drop_violation: Option<(Position, Violation)>,
+    // where the dragged job could go, cheapest first
+    insertions: Vec<Insertion>,
+    timeline_ref: NodeRef,
+    // applied after rendering, once the timeline has its new width
+    pending_scroll_left: Option<f64>,
+    file_reader: Option<FileReader>,
+    // the outcome of the last import, shown to the user
+    status: Option<String>,
+    // a CSV file waiting for its columns to be mapped
+    csv_import: Option<CsvImport>,
+    // rows which could not be imported
+    import_errors: Vec<String>,
+    history: History,
+    optimize_scope_ref: NodeRef,
+    proposal: Option<Proposal>,
+    _shortcuts: EventListener,
 }
 
 
+impl App {
+    fn save(&mut self) {
+        if let Err(message) = storage::save(&self.plans.current_key(), &self.state) {
+            self.status = Some(message);
+        }
+    }
+
+    fn open_current_plan(&mut self) {
+        let (state, status) = load_plan(&self.plans);
+        self.state = state;
+        self.status = status;
+        // each plan has its own edits to undo
+        self.history = History::default();
+    }
+
+    fn plan_action(&mut self, action: PlanAction) -> Result<String, String> {
+        // Saves the open plan before leaving it, except when deleting it.
+        match action {
+            PlanAction::Open(name) => {
+                self.save();
+                self.plans.open(&name)?;
+                self.open_current_plan();
+                Ok(format!("Opened \"{name}\""))
+            }
+            PlanAction::New(name) => {
+                self.save();
+                self.plans.create(&name)?;
+                self.state = new_state();
+                self.history = History::default();
+                self.save();
+                Ok(format!("Created \"{name}\""))
+            }
+            PlanAction::SaveAs(name) => {
+                self.save();
+                self.plans.duplicate(&name)?;
+                Ok(format!("Saved a copy as \"{name}\""))
+            }
+            PlanAction::Rename(name) => {
+                self.save();
+                self.plans.rename(&name)?;
+                Ok(format!("Renamed to \"{name}\""))
+            }
+            PlanAction::Delete => {
+                let deleted = self.plans.current.clone();
+                self.plans.delete_current();
+                self.open_current_plan();
+                Ok(format!("Deleted \"{deleted}\""))
+            }
+        }
+    }
+
+    fn import_csv(&mut self) -> Result<String, String> {
+        let csv_import = self.csv_import.take().ok_or("There is no CSV file to import")?;
+        let imported = match csv_import::import(&csv_import) {
+            Ok(imported) => imported,
+            Err(message) => {
+                // let the user fix the mapping and try again
+                self.csv_import = Some(csv_import);
+                return Err(message);
+            }
+        };
+        self.state.runs = imported.runs;
+        self.state.unassigned = imported.unassigned;
+        // a loaded matrix still applies to the new jobs, where it has their locations
+        let unmatched = matrix::resolve_locations(&mut self.state)
+            .filter(|(found, total)| found < total)
+            .map(|(found, total)| format!(", {}", matrix::describe_unmatched(found, total)))
+            .unwrap_or_default();
+        self.state.reschedule();
+        let n_errors = imported.errors.len();
+        self.import_errors = imported.errors;
+        Ok(format!(
+            "Imported {} jobs on {} runs, {} unassigned. {n_errors} rows skipped{unmatched}",
+            imported.n_jobs, self.state.runs.len(), self.state.unassigned.len(),
+        ))
+    }
+
+    fn show_dragged(&self, visible: bool) {
+        for uid in self.dragged.iter() {
+            let Some((run_idx, seq)) = self.state.find_job(uid) else { continue };
+            toggle_visible(&Position::new(run_idx, 2 * seq + 1), visible);
+        }
+    }
+
+    fn import(&mut self, kind: FileKind, contents: &str) -> Result<String, String> {
+        // Applies the contents of an imported file, and describes what happened.
+        match kind {
+            FileKind::Matrix => {
+                let travel_matrix = matrix::parse_table(contents)?;
+                let n = travel_matrix.locations.len();
+                self.state.travel_matrix = Some(Rc::new(travel_matrix));
+                self.state.travel_model = TravelModel::Matrix;
+                let (found, total) = matrix::resolve_locations(&mut self.state).unwrap_or_default();
+                self.state.reschedule();
+                Ok(format!("Loaded a {n}x{n} matrix, {}", matrix::describe_unmatched(found, total)))
+            }
+            FileKind::Csv => {
+                // the columns have to be mapped before the rows can be imported
+                self.import_errors.clear();
+                self.csv_import = Some(csv_import::read_headers(contents.to_string())?);
+                Ok("Check which column holds each field, then import".to_string())
+            }
+            FileKind::VroomProblem => vroom::import_problem(contents, &mut self.state),
+            FileKind::VroomSolution => vroom::import_solution(contents, &mut self.state),
+            FileKind::SolomonInstance => solomon::import_instance(contents, &mut self.state),
+            FileKind::SolomonSolution => solomon::import_solution(contents, &mut self.state),
+        }
+    }
+}
+
 impl Component for App {
     type Message = Msg;
     type Properties = ();
 
 
-    fn create(_ctx: &Context<Self>) -> Self {
-        let runs = LocalStorage::get("timeline_state").unwrap_or_else(|_| new_runs());
-        let state = AppState { runs, animation_strategy: Strategy::None };
+    fn create(ctx: &Context<Self>) -> Self {
+        let (plans, index_status) = PlanIndex::load();
+        let (state, plan_status) = load_plan(&plans);
+        let status = match (index_status, plan_status) {
+            (Some(index_status), Some(plan_status)) => Some(format!("{index_status} {plan_status}")),
+            (index_status, plan_status) => index_status.or(plan_status),
+        };
 
         App {
             state,
+            plans,
+            plan_name_ref: NodeRef::default(),
             drag_from_pos: None,
+            dragged: vec![],
+            selected: vec![],
+            key_target: None,
+            announcement: String::new(),
+            pending_focus: None,
+            pending_reveal: None,
+            pointer_drag: None,
+            auto_scroll: None,
             dragging_over_pos: None,
+            drop_violation: None,
+            insertions: vec![],
+            timeline_ref: NodeRef::default(),
+            pending_scroll_left: None,
+            file_reader: None,
+            status,
+            csv_import: None,
+            import_errors: vec![],
+            history: History::default(),
+            optimize_scope_ref: NodeRef::default(),
+            proposal: None,
+            _shortcuts: listen_for_shortcuts(ctx.link().clone()),
         }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::DragStart(pos) => {
                 self.drag_from_pos = Some(pos);
                 self.dragging_over_pos = Some(pos);
-                toggle_visible(&pos, false);
+                self.dragged = drag_block(pos, &self.selected, &self.state);
+                self.key_target = None;
+                self.show_dragged(false);
+                self.insertions = rank_insertions(&self.dragged, &self.state);
+                return true;
             }
             Msg::Drop(to_pos) => {
                 let Some(from_pos) = self.drag_from_pos else { return false };
                 if from_pos == to_pos { return false };
 
-                move_job(from_pos, to_pos, &mut self.state);
-                push_subsequent_jobs(&to_pos, false, &mut self.state.runs);
-
-                if let Some(pos) = self.drag_from_pos {
-                    toggle_visible(&pos, true)
+                // the hidden jobs are found by position, so show them before they move
+                self.show_dragged(true);
+                let before = self.state.clone();
+                match move_job(&self.dragged, to_pos, &mut self.state) {
+                    Ok(()) => self.history.record(Command::Move(from_pos, to_pos), before),
+                    Err(violation) => self.status = Some(violation.describe()),
                 }
-                
-                LocalStorage::set("timeline_state", &self.state).unwrap();
+                push_subsequent_jobs(&to_pos, false, &mut self.state);
+
+                self.save();
                 
                 self.dragging_over_pos = None;
                 self.drag_from_pos = None;
+                self.dragged.clear();
+                self.key_target = None;
+                self.auto_scroll = None;
+                self.drop_violation = None;
+                self.insertions.clear();
+                return true;
+            }
+            Msg::StartAutoScroll => {
+                if self.drag_from_pos.is_none() { return false };
+                self.auto_scroll = Some(AutoScroll::start(self.timeline_ref.clone(), ctx.link().clone()));
+                return false;
+            }
+            Msg::DragEnd => {
+                // the drag was cancelled, a drop has already tidied up
+                if self.drag_from_pos.take().is_none() { return false };
+                if let Some(pos) = self.dragging_over_pos.take() {
+                    push_subsequent_jobs(&pos, false, &mut self.state);
+                }
+                for job in self.state.all_jobs_mut() {
+                    job.preview_times = None;
+                }
+                self.show_dragged(true);
+                self.dragged.clear();
+                self.key_target = None;
+                self.auto_scroll = None;
+                self.drop_violation = None;
+                self.insertions.clear();
                 return true;
             }
 
             Msg::DragEnter(pos) => {
+                // already previewed, e.g. entering a child of the same leg
+                if self.dragging_over_pos == Some(pos) { return false };
                 // - ondrag needed for cursour "grab" (prevent default or wthaver)
                 match self.dragging_over_pos {
                     Some(dragging_over_pos) => {
                         if dragging_over_pos.run_idx == pos.run_idx {
                             // over the same run
-                            push_subsequent_jobs(&pos, true, &mut self.state.runs);
+                            push_subsequent_jobs(&pos, true, &mut self.state);
                         } else {
                             // over a different run
                             let prev_run_pos = Position::new(dragging_over_pos.run_idx, 0);
-                            push_subsequent_jobs(&prev_run_pos, false, &mut self.state.runs);
-                            push_subsequent_jobs(&pos, true, &mut self.state.runs);
+                            push_subsequent_jobs(&prev_run_pos, false, &mut self.state);
+                            push_subsequent_jobs(&pos, true, &mut self.state);
                         }},
                         None => {
                             // new run
-                            push_subsequent_jobs(&pos, true, &mut self.state.runs);
+                            push_subsequent_jobs(&pos, true, &mut self.state);
                     }
                 }
 
+                if self.drag_from_pos.is_some() {
+                    self.drop_violation = preview_move(&self.dragged, pos, &mut self.state)
+                        .map(|violation| (pos, violation));
+                }
+
                 self.dragging_over_pos = Some(pos);
                 return true;
             },
 
-            Msg::DragOver(_pos) => {
-                return false;
+            Msg::Select(pos, add) => {
+                let Some(job) = pos.left_job_seq().and_then(|seq| self.state.lane(pos.run_idx)?.get(seq)) else { return false };
+                let uid = job.uid.clone();
+                match (add, self.selected.iter().position(|selected| *selected == uid)) {
+                    (true, Some(idx)) => { self.selected.remove(idx); }
+                    (true, None) => self.selected.push(uid),
+                    (false, _) => self.selected = vec![uid],
+                }
+                return true;
             }
+            Msg::KeyMove(pos, key) => {
+                // keyboard moves go through the same messages as a drag
+                let Some(target) = self.key_target else {
+                    // an arrow key picks the job up, Enter and Escape do nothing until then
+                    if matches!(key, KeyMove::Commit | KeyMove::Cancel) { return false };
+                    let job = pos.left_job_seq().and_then(|seq| self.state.lane(pos.run_idx)?.get(seq));
+                    if job.is_none_or(|job| job.locked) { return false };
 
-            Msg::DragLeave(_pos) => {
+                    Component::update(self, ctx, Msg::DragStart(pos));
+                    let Some(home) = keyboard::first_target(&self.dragged, &self.state) else { return true };
+                    let target = keyboard::next_target(home, key, &self.dragged, &self.state);
+                    Component::update(self, ctx, Msg::DragEnter(target));
+                    self.key_target = Some(target);
+                    self.pending_reveal = Some(target);
+                    self.announcement = keyboard::describe_target(target, &self.dragged, &self.state);
+                    return true;
+                };
+                if self.drag_from_pos.is_none() { return false };
+                let block = self.dragged.clone();
+                match key {
+                    KeyMove::Commit if keyboard::no_op(target, &block, &self.state) => {
+                        // nothing to move, and nothing to undo
+                        Component::update(self, ctx, Msg::DragEnd);
+                        self.announcement = keyboard::describe_kept(&block, &self.state);
+                        self.pending_focus = block.first().cloned();
+                    }
+                    KeyMove::Commit => {
+                        self.status = None;
+                        Component::update(self, ctx, Msg::Drop(target));
+                        self.announcement = match &self.status {
+                            Some(refused) => refused.clone(),
+                            None => keyboard::describe_moved(&block, &self.state),
+                        };
+                        self.pending_focus = block.first().cloned();
+                    }
+                    KeyMove::Cancel => {
+                        Component::update(self, ctx, Msg::DragEnd);
+                        self.announcement = "Move cancelled".to_string();
+                        self.pending_focus = block.first().cloned();
+                    }
+                    _ => {
+                        let target = keyboard::next_target(target, key, &block, &self.state);
+                        Component::update(self, ctx, Msg::DragEnter(target));
+                        self.key_target = Some(target);
+                        self.pending_reveal = Some(target);
+                        self.announcement = keyboard::describe_target(target, &block, &self.state);
+                    }
+                }
+                return true;
+            }
+            Msg::PointerDown(pos, event) => {
+                let block = drag_block(pos, &self.selected, &self.state).iter()
+                    .filter_map(|uid| self.state.find_job(uid))
+                    .map(|(run_idx, seq)| Position::new(run_idx, 2 * seq + 1))
+                    .collect();
+                self.pointer_drag = Some(PointerDrag::start(pos, block, &event, ctx.link().clone()));
+                return false;
+            }
+            Msg::PointerDone => {
+                self.pointer_drag = None;
                 return false;
             }
+            Msg::ToggleLock(pos) => {
+                let before = self.state.clone();
+                let Some(seq) = pos.left_job_seq() else { return false };
+                let Some(job) = self.state.lane_mut(pos.run_idx).and_then(|jobs| jobs.get_mut(seq)) else { return false };
+                job.locked = !job.locked;
+                let command = Command::Lock(job.uid.clone(), job.locked);
+                self.history.record(command, before);
+                self.save();
+                return true;
+            }
+            Msg::DragOver(_pos) => {}
+
+            Msg::DragLeave(_pos) => {}
             Msg::Reset => {
+                self.history.record(Command::Reset, self.state.clone());
                 self.state.runs = new_runs();
-                LocalStorage::delete("timeline_state");
+                self.state.unassigned = new_unassigned(12);
+                // a loaded matrix does not know the new random locations
+                self.state.travel_matrix = None;
+                self.state.travel_model = TravelModel::default();
+                self.state.reschedule();
+                self.save();
+                return true;
+            }
+            Msg::Zoom(factor, anchor_offset) => {
+                let old_px_per_minute = self.state.px_per_minute;
+                self.state.px_per_minute = zoom::zoom(old_px_per_minute, factor);
+                self.pending_scroll_left = zoom::scroll_to_keep_anchor(
+                    &self.timeline_ref, anchor_offset, old_px_per_minute, self.state.px_per_minute,
+                );
+                self.save();
+                return true;
+            }
+            Msg::Pan(minutes) => {
+                zoom::pan(&self.timeline_ref, minutes, self.state.px_per_minute);
+                return false;
+            }
+            Msg::ReadFile(kind, file) => {
+                self.file_reader = Some(read_file(kind, &file, ctx.link().clone()));
+                return false;
+            }
+            Msg::MapCsvColumn(field, column) => {
+                let Some(csv_import) = &mut self.csv_import else { return false };
+                match column {
+                    Some(column) => csv_import.mapping.insert(field, column),
+                    None => csv_import.mapping.remove(&field),
+                };
+                return true;
+            }
+            Msg::ImportCsv => {
+                let before = self.state.clone();
+                self.status = Some(match self.import_csv() {
+                    Ok(message) => {
+                        self.history.record(Command::Import(FileKind::Csv), before);
+                        self.save();
+                        message
+                    }
+                    Err(message) => message,
+                });
+                return true;
+            }
+            Msg::Export(kind) => {
+                let plan = &self.plans.current;
+                let result = match kind {
+                    ExportKind::RouteSheet => export::route_sheet_csv(&self.state)
+                        .and_then(|csv| export::download(&format!("{plan}.csv"), "text/csv", &csv)),
+                    ExportKind::Json => export::plan_json(&self.state)
+                        .and_then(|json| export::download(&format!("{plan}.json"), "application/json", &json)),
+                    ExportKind::Vroom => vroom::export_solution(&self.state)
+                        .and_then(|json| export::download(&format!("{plan}-solution.json"), "application/json", &json)),
+                };
+                let Err(message) = result else { return false };
+                self.status = Some(message);
+                return true;
+            }
+            Msg::Optimize(scope) => {
+                self.proposal = Some(optimize::propose(&self.state, scope));
+                self.status = None;
+                return true;
+            }
+            Msg::ApplyOptimized => {
+                let Some(proposal) = self.proposal.take() else { return false };
+                if proposal.base != optimize::job_order(&self.state.runs) {
+                    self.status = Some("The plan has changed since it was optimized, optimize it again".to_string());
+                    return true;
+                }
+                self.history.record(Command::Optimize, self.state.clone());
+                self.state.runs = proposal.runs;
+                self.state.reschedule();
+                self.save();
+                return true;
+            }
+            Msg::DiscardOptimized => {
+                self.proposal = None;
+                return true;
+            }
+            Msg::CancelCsv => {
+                self.csv_import = None;
+                self.status = None;
+                return true;
+            }
+            Msg::FileRead(kind, contents) => {
+                self.file_reader = None;
+                let before = self.state.clone();
+                self.status = Some(match contents.and_then(|contents| self.import(kind, &contents)) {
+                    Ok(message) => {
+                        // some imports only change the plan in a later step
+                        if self.state != before {
+                            self.history.record(Command::Import(kind), before);
+                        }
+                        message
+                    }
+                    Err(message) => message,
+                });
+                self.save();
+                return true;
+            }
+            Msg::SetTravelModel(model) => {
+                self.history.record(Command::SetTravelModel(model.clone()), self.state.clone());
+                self.state.travel_model = model;
+                self.state.reschedule();
+                self.save();
+                return true;
+            }
+            Msg::Plan(action) => {
+                self.status = Some(match self.plan_action(action) {
+                    Ok(message) => message,
+                    Err(message) => message,
+                });
+                return true;
+            }
+            Msg::Undo => {
+                let Some(command) = self.history.undo(&mut self.state) else { return false };
+                self.status = Some(format!("Undid {}", command.describe()));
+                self.save();
+                return true;
+            }
+            Msg::Redo => {
+                let Some(command) = self.history.redo(&mut self.state) else { return false };
+                self.status = Some(format!("Redid {}", command.describe()));
+                self.save();
                 return true;
             }
             Msg::SetAnimationStrategy(strategy) => {
                 self.state.animation_strategy = strategy;
+                self.save();
                 return true;
             }
             // Msg::ToggleAnimations => {
@@ -206,21 +893,142 @@ impl Component for App {
         false
     }
 
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if let Some(scroll_left) = self.pending_scroll_left.take() {
+            zoom::set_scroll_left(&self.timeline_ref, scroll_left);
+        }
+        if let Some(uid) = self.pending_focus.take() {
+            keyboard::focus_job(&uid, &self.state);
+        }
+        if let Some(target) = self.pending_reveal.take() {
+            keyboard::reveal(&target);
+        }
+    }
+
     fn view(&self, ctx: &Context<Self>) -> Html {
         let reset = ctx.link().callback(|_| Msg::Reset);
+
+        let plan_name_ref = self.plan_name_ref.clone();
+        let plan_button = move |action: fn(String) -> PlanAction| {
+            let plan_name_ref = plan_name_ref.clone();
+            ctx.link().callback(move |_| {
+                let name = plan_name_ref.cast::<HtmlInputElement>().map(|input| input.value()).unwrap_or_default();
+                Msg::Plan(action(name.trim().to_string()))
+            })
+        };
+        let new_plan = plan_button(PlanAction::New);
+        let save_plan_as = plan_button(PlanAction::SaveAs);
+        let rename_plan = plan_button(PlanAction::Rename);
+        let deleted_name = self.plans.current.clone();
+        let delete_plan = ctx.link().batch_callback(move |_| {
+            // a deleted plan cannot be undone, so ask first
+            let message = format!("Delete the plan \"{deleted_name}\"? This cannot be undone.");
+            let confirmed = window().confirm_with_message(&message).unwrap_or(false);
+            confirmed.then_some(Msg::Plan(PlanAction::Delete))
+        });
+        let open_plan = ctx.link().callback(|event: Event| {
+            let name = event.target_unchecked_into::<HtmlSelectElement>().value();
+            Msg::Plan(PlanAction::Open(name))
+        });
+        let optimize_scope_ref = self.optimize_scope_ref.clone();
+        let optimize = ctx.link().callback(move |_| {
+            // the scope is a run index, or empty for all runs
+            let scope = optimize_scope_ref.cast::<HtmlSelectElement>().and_then(|select| select.value().parse().ok());
+            Msg::Optimize(scope)
+        });
+        let dragged_jobs = self.dragged.iter()
+            .filter_map(|uid| {
+                let (run_idx, seq) = self.state.find_job(uid)?;
+                self.state.lane(run_idx)?.get(seq)
+            });
+        let drag_gap = push_gap(dragged_jobs, self.state.px_per_minute);
+        let undo = ctx.link().callback(|_| Msg::Undo);
+        let redo = ctx.link().callback(|_| Msg::Redo);
+        let undo_title = self.history.next_undo().map(|command| format!("Undo {} (Ctrl+Z)", command.describe()));
+        let redo_title = self.history.next_redo().map(|command| format!("Redo {} (Ctrl+Shift+Z)", command.describe()));
+        let zoom_in = ctx.link().callback(|_| Msg::Zoom(zoom::ZOOM_STEP, None));
+        let zoom_out = ctx.link().callback(|_| Msg::Zoom(1.0 / zoom::ZOOM_STEP, None));
+        let pan_earlier = ctx.link().callback(|_| Msg::Pan(-zoom::PAN_MINUTES));
+        let pan_later = ctx.link().callback(|_| Msg::Pan(zoom::PAN_MINUTES));
+
+        let timeline_ref = self.timeline_ref.clone();
+        let on_wheel = ctx.link().batch_callback(move |event: WheelEvent| {
+            // plain wheel scrolls as usual, ctrl+wheel zooms about the pointer
+            if !event.ctrl_key() { return None };
+            event.prevent_default();
+            let anchor_offset = zoom::pointer_offset(&timeline_ref, event.client_x());
+            Some(Msg::Zoom(zoom::wheel_factor(&event), anchor_offset))
+        });
         let callback_mgr = CallbackMgr::new(ctx.link().clone());
 
         let on_input_change = ctx.link().callback(move |event: Event| {
             let Some(target) = event.target() else { return Msg::SetAnimationStrategy(Strategy::None) };
             let value = target.unchecked_into::<HtmlInputElement>().value();
-            let Ok(selected) = value.parse::<String>() else { return Msg::SetAnimationStrategy(Strategy::None) };
-            let strategy = Strategy::from_str(&selected).unwrap_or(Strategy::None);
+            let strategy = Strategy::from_str(&value).unwrap_or(Strategy::None);
 
             Msg::SetAnimationStrategy(strategy)
         });
 
+        let on_travel_change = ctx.link().callback(move |event: Event| {
+            let Some(target) = event.target() else { return Msg::SetTravelModel(TravelModel::default()) };
+            let value = target.unchecked_into::<HtmlInputElement>().value();
+            let model = TravelModel::from_str(&value).unwrap_or_default();
+
+            Msg::SetTravelModel(model)
+        });
+
+        let on_matrix_file = ctx.link().batch_callback(|event: Event| {
+            selected_file(&event).map(|file| Msg::ReadFile(FileKind::Matrix, file))
+        });
+        let on_csv_file = ctx.link().batch_callback(|event: Event| {
+            selected_file(&event).map(|file| Msg::ReadFile(FileKind::Csv, file))
+        });
+        let on_vroom_problem_file = ctx.link().batch_callback(|event: Event| {
+            selected_file(&event).map(|file| Msg::ReadFile(FileKind::VroomProblem, file))
+        });
+        let on_vroom_solution_file = ctx.link().batch_callback(|event: Event| {
+            selected_file(&event).map(|file| Msg::ReadFile(FileKind::VroomSolution, file))
+        });
+        let on_solomon_instance_file = ctx.link().batch_callback(|event: Event| {
+            selected_file(&event).map(|file| Msg::ReadFile(FileKind::SolomonInstance, file))
+        });
+        let on_solomon_solution_file = ctx.link().batch_callback(|event: Event| {
+            selected_file(&event).map(|file| Msg::ReadFile(FileKind::SolomonSolution, file))
+        });
+
+        // the timeline spans from the earliest run start to the latest run end.
+        let origin = self.state.runs.iter().map(|run| run.start_time).min().unwrap_or(0);
+        let horizon = self.state.runs.iter()
+            .map(|run| run.end_time.max(run.return_time))
+            .max()
+            .unwrap_or(origin);
+
         html! {
             <>
+                <div class="plans">
+                    <select onchange={open_plan}>
+                        { for self.plans.names.iter().map(|name| html! {
+                            <option value={name.clone()} selected={*name == self.plans.current}>{name}</option>
+                        })}
+                    </select>
+                    <input type="text" placeholder="plan name" ref={self.plan_name_ref.clone()} />
+                    <button onclick={new_plan}>{"New"}</button>
+                    <button onclick={save_plan_as}>{"Save as"}</button>
+                    <button onclick={rename_plan}>{"Rename"}</button>
+                    <button onclick={delete_plan}>{"Delete"}</button>
+                </div>
+                <div class="zoom">
+                    <button onclick={pan_earlier}>{"◀"}</button>
+                    <button onclick={zoom_out}>{"−"}</button>
+                    <button onclick={zoom_in}>{"+"}</button>
+                    <button onclick={pan_later}>{"▶"}</button>
+                </div>
+                <div class="timeline" ref={ self.timeline_ref.clone() } onwheel={ on_wheel }>
+                <RulerComponent
+                    start_time={ origin }
+                    end_time={ horizon }
+                    px_per_minute={ self.state.px_per_minute }
+                />
                 { for self.state.runs.iter().enumerate().map(move|(run_idx, run)| html! {
                     <RunComponent 
                         run_idx={run_idx}
@@ -228,10 +1036,27 @@ impl Component for App {
                         color={run.color.clone()}
                         start_time={run.start_time}
                         end_time={run.end_time}
+                        return_time={run.return_time}
+                        capacity={run.capacity.clone()}
+                        origin={ origin }
+                        px_per_minute={ self.state.px_per_minute }
+                        drop_violation={ self.drop_violation.filter(|(pos, _)| pos.run_idx == run_idx) }
+                        insertions={ self.insertions.iter().filter(|insertion| insertion.pos.run_idx == run_idx).copied().collect::<Vec<_>>() }
+                        dragging_over_pos={ self.dragging_over_pos }
+                        selected={ self.selected.clone() }
+                        push_gap={ drag_gap }
                         animation_strategy={self.state.animation_strategy.clone()}
                         callback_mgr={ callback_mgr.clone() }
                     />
                 })}
+                </div>
+                <PoolComponent
+                    jobs={ self.state.unassigned.clone() }
+                    selected={ self.selected.clone() }
+                    px_per_minute={ self.state.px_per_minute }
+                    animation_strategy={ self.state.animation_strategy.clone() }
+                    callback_mgr={ CallbackMgr::new(ctx.link().clone()) }
+                />
                 <br /><br />
                 <div class="control">
                     <label class="radio">
@@ -244,9 +1069,84 @@ impl Component for App {
                         })}
                     </label>
                 </div>
+                <div class="control">
+                    <label class="radio">
+                        {
+                            for TravelModel::VARIANTS.iter().map(|model| html! {
+                                <label>
+                                    <input type="radio" name="travel" onchange={on_travel_change.clone()} value={model.to_string()} checked={self.state.travel_model.to_string() == *model} />
+                                    {model}
+                                </label>
+                        })}
+                    </label>
+                    <label>
+                        {"Load travel matrix "}
+                        <input type="file" accept=".json" onchange={on_matrix_file} />
+                    </label>
+                    <label>
+                        {"Import jobs from CSV "}
+                        <input type="file" accept=".csv,text/csv" onchange={on_csv_file} />
+                    </label>
+                    <label>
+                        {"Load VROOM problem "}
+                        <input type="file" accept=".json" onchange={on_vroom_problem_file} />
+                    </label>
+                    <label>
+                        {"Load VROOM solution "}
+                        <input type="file" accept=".json" onchange={on_vroom_solution_file} />
+                    </label>
+                    <label>
+                        {"Load Solomon instance "}
+                        <input type="file" accept=".txt" onchange={on_solomon_instance_file} />
+                    </label>
+                    <label>
+                        {"Load Solomon solution "}
+                        <input type="file" accept=".txt,.sol" onchange={on_solomon_solution_file} />
+                    </label>
+                </div>
+                if let Some(csv_import) = &self.csv_import {
+                    <CsvMappingComponent
+                        headers={ csv_import.headers.clone() }
+                        mapping={ csv_import.mapping.clone() }
+                        on_change={ ctx.link().callback(|(field, column)| Msg::MapCsvColumn(field, column)) }
+                        on_import={ ctx.link().callback(|_| Msg::ImportCsv) }
+                        on_cancel={ ctx.link().callback(|_| Msg::CancelCsv) }
+                    />
+                }
+                <div class="optimize">
+                    <select ref={self.optimize_scope_ref.clone()}>
+                        <option value="">{"all runs"}</option>
+                        { for self.state.runs.iter().enumerate().map(|(run_idx, run)| html! {
+                            <option value={run_idx.to_string()}>{ run.label(run_idx) }</option>
+                        })}
+                    </select>
+                    <button onclick={optimize}>{"Optimize"}</button>
+                </div>
+                if let Some(proposal) = &self.proposal {
+                    <OptimizeDiffComponent
+                        proposal={ proposal.clone() }
+                        vehicles={ self.state.runs.iter().enumerate().map(|(run_idx, run)| run.label(run_idx)).collect::<Vec<_>>() }
+                        on_apply={ ctx.link().callback(|_| Msg::ApplyOptimized) }
+                        on_discard={ ctx.link().callback(|_| Msg::DiscardOptimized) }
+                    />
+                }
+                if let Some(status) = &self.status {
+                    <div class="status">{ status }</div>
+                }
+                <div class="sr-only" aria-live="polite">{ &self.announcement }</div>
+                if !self.import_errors.is_empty() {
+                    <ul class="import-errors">
+                        { for self.import_errors.iter().map(|error| html! { <li>{ error }</li> }) }
+                    </ul>
+                }
                     
                 <br /><br />
+                <button onclick={undo} disabled={self.history.next_undo().is_none()} title={undo_title}>{"Undo"}</button>
+                <button onclick={redo} disabled={self.history.next_redo().is_none()} title={redo_title}>{"Redo"}</button>
                 <button onclick={reset}>{"Reset"}</button>
+                <button onclick={ctx.link().callback(|_| Msg::Export(ExportKind::RouteSheet))}>{"Export route sheet (CSV)"}</button>
+                <button onclick={ctx.link().callback(|_| Msg::Export(ExportKind::Json))}>{"Export plan (JSON)"}</button>
+                <button onclick={ctx.link().callback(|_| Msg::Export(ExportKind::Vroom))}>{"Export VROOM solution"}</button>
 
             </>
         }
@@ -257,3 +1157,19 @@ impl Component for App {
 pub fn main() {
     yew::Renderer::<App>::new().render();
 }
+
+// Fixture helpers shared by the modules' tests, on top of the demo plan from new_state.
+#[cfg(test)]
+mod testing {
+    use crate::{AppState, RunIdx};
+
+    // the uids of the jobs in a lane, in order
+    pub fn uids(state: &AppState, run_idx: RunIdx) -> Vec<String> {
+        state.lane(run_idx).unwrap().iter().map(|job| job.uid.clone()).collect()
+    }
+
+    // a block of jobs to move
+    pub fn block(uids: &[&str]) -> Vec<String> {
+        uids.iter().map(|uid| uid.to_string()).collect()
+    }
+}
@@ -0,0 +1,168 @@
+use serde::Deserialize;
+
+use crate::{AppState, locations::Location, travel::TravelMatrix};
+
+// How close (in degrees) a job must be to a matrix location to use its row.
+const SAME_PLACE: f32 = 1e-5;
+
+#[derive(Deserialize)]
+struct Waypoint {
+    // [lon, lat], as OSRM returns them
+    location: [f32; 2],
+}
+
+// The response of OSRM's table service, with the coordinates that were
+// requested listed either in `locations` or in `sources`.
+#[derive(Deserialize)]
+struct Table {
+    #[serde(default)]
+    locations: Vec<[f32; 2]>,
+    #[serde(default)]
+    sources: Vec<Waypoint>,
+    // seconds, null where there is no route
+    durations: Vec<Vec<Option<f32>>>,
+    // metres
+    #[serde(default)]
+    distances: Vec<Vec<Option<f32>>>,
+}
+
+pub fn per_minute(seconds: Vec<Vec<Option<f32>>>) -> Vec<Vec<Option<f32>>> {
+    seconds.into_iter()
+        .map(|row| row.into_iter().map(|cell| cell.map(|secs| secs / 60.0)).collect())
+        .collect()
+}
+
+pub fn per_km(metres: Vec<Vec<Option<f32>>>) -> Vec<Vec<Option<f32>>> {
+    metres.into_iter()
+        .map(|row| row.into_iter().map(|cell| cell.map(|m| m / 1000.0)).collect())
+        .collect()
+}
+
+fn is_square(rows: &[Vec<Option<f32>>], n: usize) -> bool {
+    rows.len() == n && rows.iter().all(|row| row.len() == n)
+}
+
+pub fn parse_table(json: &str) -> Result<TravelMatrix, String> {
+    let table: Table = serde_json::from_str(json).map_err(|err| format!("Not a travel matrix: {err}"))?;
+
+    let coords = match table.locations.is_empty() {
+        true => table.sources.iter().map(|waypoint| waypoint.location).collect(),
+        false => table.locations,
+    };
+    let locations: Vec<Location> = coords.iter().enumerate()
+        .map(|(idx, [lon, lat])| Location { lat: *lat, lon: *lon, matrix_idx: Some(idx) })
+        .collect();
+
+    let n = locations.len();
+    if !is_square(&table.durations, n) {
+        return Err(format!("durations should be a {n}x{n} matrix, one row per location"));
+    }
+    if !table.distances.is_empty() && !is_square(&table.distances, n) {
+        return Err(format!("distances should be a {n}x{n} matrix, one row per location"));
+    }
+
+    Ok(TravelMatrix {
+        durations: per_minute(table.durations),
+        distances: per_km(table.distances),
+        locations,
+    })
+}
+
+fn resolve(location: &mut Location, known: &[Location]) -> bool {
+    location.matrix_idx = known.iter()
+        .find(|other| (other.lat - location.lat).abs() < SAME_PLACE && (other.lon - location.lon).abs() < SAME_PLACE)
+        .and_then(|other| other.matrix_idx);
+    location.matrix_idx.is_some()
+}
+
+pub fn resolve_locations(state: &mut AppState) -> Option<(usize, usize)> {
+    // Points every job (on a run or not) and depot at its row of the plan's matrix.
+    // Returns how many locations were found in the matrix, out of how many there are,
+    // or None if the matrix does not say where its rows are.
+    let matrix = state.travel_matrix.clone().filter(|matrix| !matrix.locations.is_empty())?;
+    let mut found = 0;
+    let mut total = 0;
+    for run in state.runs.iter_mut() {
+        let depots = [&mut run.start_depot, &mut run.end_depot];
+        let jobs = run.jobs.iter_mut().map(|job| &mut job.location);
+        for location in depots.into_iter().chain(jobs) {
+            total += 1;
+            if resolve(location, &matrix.locations) { found += 1 };
+        }
+    }
+    for job in state.unassigned.iter_mut() {
+        total += 1;
+        if resolve(&mut job.location, &matrix.locations) { found += 1 };
+    }
+    Some((found, total))
+}
+
+pub fn describe_unmatched(found: usize, total: usize) -> String {
+    format!("{} of {total} locations are not in the travel matrix", total - found)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::new_state;
+
+    #[test]
+    fn resolves_jobs_in_the_pool_too() {
+        let mut state = new_state();
+        let on_run = state.runs[0].jobs[0].location.clone();
+        let in_pool = state.unassigned[0].location.clone();
+        let locations = [on_run, in_pool].into_iter().enumerate()
+            .map(|(idx, location)| Location { matrix_idx: Some(idx), ..location })
+            .collect();
+        state.travel_matrix = Some(Rc::new(TravelMatrix { durations: vec![vec![Some(0.0); 2]; 2], distances: vec![], locations }));
+
+        // 3 runs with 2 depots and 4 jobs each, and 3 jobs in the pool
+        assert_eq!(resolve_locations(&mut state), Some((2, 21)));
+        assert_eq!(state.runs[0].jobs[0].location.matrix_idx, Some(0));
+        assert_eq!(state.unassigned[0].location.matrix_idx, Some(1));
+        assert_eq!(state.unassigned[1].location.matrix_idx, None);
+    }
+
+    #[test]
+    fn leaves_indexed_matrices_alone() {
+        let mut state = new_state();
+        state.travel_matrix = Some(Rc::new(TravelMatrix::default()));
+        state.unassigned[0].location.matrix_idx = Some(3);
+        assert_eq!(resolve_locations(&mut state), None);
+        assert_eq!(state.unassigned[0].location.matrix_idx, Some(3));
+    }
+
+    #[test]
+    fn reads_locations_and_converts_units() {
+        let json = r#"{
+            "locations": [[0.5, 51.0], [1.5, 52.0]],
+            "durations": [[0, 90], [120, null]],
+            "distances": [[0, 1500], [2500, 0]]
+        }"#;
+        let matrix = parse_table(json).unwrap();
+        assert_eq!(matrix.locations[1], Location { lat: 52.0, lon: 1.5, matrix_idx: Some(1) });
+        assert_eq!(matrix.durations, [vec![Some(0.0), Some(1.5)], vec![Some(2.0), None]]);
+        assert_eq!(matrix.distances, [vec![Some(0.0), Some(1.5)], vec![Some(2.5), Some(0.0)]]);
+    }
+
+    #[test]
+    fn reads_osrm_sources() {
+        let json = r#"{
+            "sources": [{ "location": [0.5, 51.0] }, { "location": [1.5, 52.0] }],
+            "durations": [[0, 60], [60, 0]]
+        }"#;
+        let matrix = parse_table(json).unwrap();
+        assert_eq!(matrix.locations[0], Location { lat: 51.0, lon: 0.5, matrix_idx: Some(0) });
+        assert!(matrix.distances.is_empty());
+    }
+
+    #[test]
+    fn refuses_tables_that_are_not_square() {
+        let json = r#"{ "locations": [[0, 0], [1, 1]], "durations": [[0, 60]] }"#;
+        assert_eq!(parse_table(json).err().unwrap(), "durations should be a 2x2 matrix, one row per location");
+        let json = r#"{ "locations": [[0, 0], [1, 1]], "durations": [[0, 60], [60, 0]], "distances": [[0, 1, 2], [1, 0, 2]] }"#;
+        assert_eq!(parse_table(json).err().unwrap(), "distances should be a 2x2 matrix, one row per location");
+    }
+}
@@ -0,0 +1,366 @@
+// Local search over the runs: 2-opt and or-opt within a run, relocate and exchange between runs.
+// A move is only kept if it makes the plan cheaper without adding to its capacity or time window
+// violations, so a plan that keeps to its windows and capacities always still does.
+// Locked jobs keep their place in their run.
+use yew::prelude::*;
+
+use crate::{AppState, Job, Run, RunIdx};
+use crate::{capacity::overload, pairs::in_order, travel::{TravelTimeProvider, provider}};
+use crate::schedule::{schedule_run, lateness, overrun, travel_minutes};
+
+// the longest chain of jobs that or-opt moves in one go
+const MAX_SEGMENT: usize = 3;
+// caps on the number of improvements, and on the work done looking for them, so a big plan
+// cannot hang the page; work counts the jobs copied and rescheduled for each move tried
+const MAX_MOVES: usize = 1000;
+const MAX_WORK: usize = 1_000_000;
+// smaller changes than this are rounding, not improvements
+const EPSILON: f32 = 1e-3;
+
+// Compared in order: first capacity, then lateness at jobs, then working past the end of shifts,
+// then driving.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cost {
+    pub overload: f32,
+    pub lateness: f32,
+    pub overrun: f32,
+    pub travel: f32,
+}
+
+impl Cost {
+    fn of(run: &Run, travel: &dyn TravelTimeProvider) -> Cost {
+        Cost {
+            overload: overload(&run.jobs, run.capacity.as_ref()),
+            lateness: lateness(run),
+            overrun: overrun(run),
+            travel: travel_minutes(run, travel),
+        }
+    }
+
+    fn add(&self, other: &Cost) -> Cost {
+        Cost {
+            overload: self.overload + other.overload,
+            lateness: self.lateness + other.lateness,
+            overrun: self.overrun + other.overrun,
+            travel: self.travel + other.travel,
+        }
+    }
+
+    fn better_than(&self, other: &Cost) -> bool {
+        let pairs = [
+            (self.overload, other.overload),
+            (self.lateness, other.lateness),
+            (self.overrun, other.overrun),
+            (self.travel, other.travel),
+        ];
+        for (mine, theirs) in pairs {
+            if mine < theirs - EPSILON { return true };
+            if mine > theirs + EPSILON { return false };
+        }
+        false
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("{:.0} min driving", self.travel)];
+        if self.lateness > 0.0 {
+            parts.push(format!("{:.0} min late", self.lateness));
+        }
+        if self.overrun > 0.0 {
+            parts.push(format!("{:.0} min over shift", self.overrun));
+        }
+        if self.overload > 0.0 {
+            parts.push(format!("{:.0} over capacity", self.overload));
+        }
+        parts.join(", ")
+    }
+}
+
+pub fn plan_cost(runs: &[Run], travel: &dyn TravelTimeProvider) -> Cost {
+    runs.iter().fold(Cost::default(), |total, run| total.add(&Cost::of(run, travel)))
+}
+
+pub fn locks_kept(before: &[Job], after: &[Job]) -> bool {
+    // every locked job in `before` is still at the same place in `after`
+    before.iter().enumerate()
+        .filter(|(_, job)| job.locked)
+        .all(|(seq, job)| after.get(seq).is_some_and(|other| other.uid == job.uid))
+}
+
+struct Search<'a> {
+    runs: Vec<Run>,
+    costs: Vec<Cost>,
+    travel: &'a dyn TravelTimeProvider,
+    work: usize,
+}
+
+impl Search<'_> {
+    fn out_of_work(&self) -> bool {
+        self.work >= MAX_WORK
+    }
+
+    fn try_jobs(&mut self, changes: Vec<(RunIdx, Vec<Job>)>) -> bool {
+        // Keeps the new job orders if they cost less, in total, than the runs they replace.
+        self.work += changes.iter().map(|(_, jobs)| jobs.len().max(1)).sum::<usize>();
+        let allowed = |(run_idx, jobs): &(RunIdx, Vec<Job>)| {
+            in_order(jobs) && locks_kept(&self.runs[*run_idx].jobs, jobs)
+        };
+        if !changes.iter().all(allowed) { return false };
+
+        let trials: Vec<(RunIdx, Run, Cost)> = changes.into_iter().map(|(run_idx, jobs)| {
+            let mut run = Run { jobs, ..self.runs[run_idx].clone() };
+            schedule_run(&mut run, self.travel);
+            let cost = Cost::of(&run, self.travel);
+            (run_idx, run, cost)
+        }).collect();
+
+        let before = trials.iter().fold(Cost::default(), |total, (run_idx, _, _)| total.add(&self.costs[*run_idx]));
+        let after = trials.iter().fold(Cost::default(), |total, (_, _, cost)| total.add(cost));
+        if !after.better_than(&before) { return false };
+
+        for (run_idx, run, cost) in trials {
+            self.runs[run_idx] = run;
+            self.costs[run_idx] = cost;
+        }
+        true
+    }
+
+    fn two_opt(&mut self, run_idx: RunIdx) -> bool {
+        // reverses a stretch of the run
+        let n = self.runs[run_idx].jobs.len();
+        for i in 0..n {
+            for j in i + 1..n {
+                if self.out_of_work() { return false };
+                let mut jobs = self.runs[run_idx].jobs.clone();
+                jobs[i..=j].reverse();
+                if self.try_jobs(vec![(run_idx, jobs)]) { return true };
+            }
+        }
+        false
+    }
+
+    fn or_opt(&mut self, run_idx: RunIdx) -> bool {
+        // moves a short chain of jobs elsewhere in the same run, keeping its order
+        let n = self.runs[run_idx].jobs.len();
+        for len in 1..=MAX_SEGMENT.min(n) {
+            for start in 0..=n - len {
+                for insert_at in 0..=n - len {
+                    if insert_at == start { continue };
+                    if self.out_of_work() { return false };
+                    let mut jobs = self.runs[run_idx].jobs.clone();
+                    let segment: Vec<Job> = jobs.drain(start..start + len).collect();
+                    jobs.splice(insert_at..insert_at, segment);
+                    if self.try_jobs(vec![(run_idx, jobs)]) { return true };
+                }
+            }
+        }
+        false
+    }
+
+    fn relocate(&mut self, from_idx: RunIdx, to_idx: RunIdx) -> bool {
+        // moves one job to another run. Pickups and deliveries stay where they are,
+        // as they would have to move with their partner.
+        for seq in 0..self.runs[from_idx].jobs.len() {
+            if self.runs[from_idx].jobs[seq].pair.is_some() { continue };
+            for insert_at in 0..=self.runs[to_idx].jobs.len() {
+                if self.out_of_work() { return false };
+                let mut from_jobs = self.runs[from_idx].jobs.clone();
+                let mut to_jobs = self.runs[to_idx].jobs.clone();
+                to_jobs.insert(insert_at, from_jobs.remove(seq));
+                if self.try_jobs(vec![(from_idx, from_jobs), (to_idx, to_jobs)]) { return true };
+            }
+        }
+        false
+    }
+
+    fn exchange(&mut self, a_idx: RunIdx, b_idx: RunIdx) -> bool {
+        // swaps a job on one run with a job on another
+        for a_seq in 0..self.runs[a_idx].jobs.len() {
+            if self.runs[a_idx].jobs[a_seq].pair.is_some() { continue };
+            for b_seq in 0..self.runs[b_idx].jobs.len() {
+                if self.runs[b_idx].jobs[b_seq].pair.is_some() { continue };
+                if self.out_of_work() { return false };
+                let mut a_jobs = self.runs[a_idx].jobs.clone();
+                let mut b_jobs = self.runs[b_idx].jobs.clone();
+                std::mem::swap(&mut a_jobs[a_seq], &mut b_jobs[b_seq]);
+                if self.try_jobs(vec![(a_idx, a_jobs), (b_idx, b_jobs)]) { return true };
+            }
+        }
+        false
+    }
+
+    fn improve(&mut self, scope: Option<RunIdx>) -> bool {
+        // makes the first improving move it finds
+        let run_indices: Vec<RunIdx> = match scope {
+            Some(run_idx) => vec![run_idx],
+            None => (0..self.runs.len()).collect(),
+        };
+        for &run_idx in run_indices.iter() {
+            if self.two_opt(run_idx) || self.or_opt(run_idx) { return true };
+        }
+        if scope.is_some() { return false };
+        for &a_idx in run_indices.iter() {
+            for &b_idx in run_indices.iter() {
+                if a_idx == b_idx { continue };
+                if self.relocate(a_idx, b_idx) { return true };
+                if a_idx < b_idx && self.exchange(a_idx, b_idx) { return true };
+            }
+        }
+        false
+    }
+}
+
+pub fn optimize(state: &AppState, scope: Option<RunIdx>) -> Vec<Run> {
+    // Improves one run (`scope`) on its own, or all runs together, and returns the new runs.
+    let travel = provider(&state.travel_model, state.travel_matrix.as_deref());
+    let costs = state.runs.iter().map(|run| Cost::of(run, travel.as_ref())).collect();
+    let mut search = Search { runs: state.runs.clone(), costs, travel: travel.as_ref(), work: 0 };
+
+    let mut moves = 0;
+    while moves < MAX_MOVES && !search.out_of_work() && search.improve(scope) {
+        moves += 1;
+    }
+    search.runs
+}
+
+// An optimized plan, waiting to be applied or discarded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Proposal {
+    pub runs: Vec<Run>,
+    // the job order it was optimized from, so that a stale proposal is not applied
+    pub base: Vec<Vec<String>>,
+    pub before: Cost,
+    pub after: Cost,
+}
+
+pub fn job_order(runs: &[Run]) -> Vec<Vec<String>> {
+    runs.iter().map(|run| run.jobs.iter().map(|job| job.uid.clone()).collect()).collect()
+}
+
+pub fn propose(state: &AppState, scope: Option<RunIdx>) -> Proposal {
+    let travel = provider(&state.travel_model, state.travel_matrix.as_deref());
+    let runs = optimize(state, scope);
+    Proposal {
+        before: plan_cost(&state.runs, travel.as_ref()),
+        after: plan_cost(&runs, travel.as_ref()),
+        base: job_order(&state.runs),
+        runs,
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct OptimizeDiffProps {
+    pub proposal: Proposal,
+    pub vehicles: Vec<String>,
+    pub on_apply: Callback<MouseEvent>,
+    pub on_discard: Callback<MouseEvent>,
+}
+
+fn render_sequence(uids: &[String], other: &[String], class: &str) -> Html {
+    // jobs that are not in the other sequence are marked
+    html! {
+        <span class="optimize-sequence">
+            { for uids.iter().map(|uid| {
+                let class = if other.contains(uid) { "optimize-job".to_string() } else { format!("optimize-job {class}") };
+                html! { <span class={ class }>{ uid }</span> }
+            })}
+        </span>
+    }
+}
+
+#[function_component(OptimizeDiffComponent)]
+pub fn optimize_diff(props: &OptimizeDiffProps) -> Html {
+    let after = job_order(&props.proposal.runs);
+    let changed: Vec<(usize, &Vec<String>, &Vec<String>)> = props.proposal.base.iter().zip(after.iter())
+        .enumerate()
+        .filter(|(_, (before, after))| before != after)
+        .map(|(run_idx, (before, after))| (run_idx, before, after))
+        .collect();
+
+    html! {
+        <div class="optimize-diff">
+            <div>{ format!("Before: {}", props.proposal.before.describe()) }</div>
+            <div>{ format!("After: {}", props.proposal.after.describe()) }</div>
+            if changed.is_empty() {
+                <div>{"No better order was found"}</div>
+            }
+            { for changed.into_iter().map(|(run_idx, before, after)| html! {
+                <div class="optimize-run">
+                    <span class="optimize-vehicle">{ props.vehicles.get(run_idx).cloned().unwrap_or_default() }</span>
+                    { render_sequence(before, after, "removed") }
+                    {"→"}
+                    { render_sequence(after, before, "added") }
+                </div>
+            })}
+            <button onclick={ props.on_apply.clone() }>{"Apply"}</button>
+            <button onclick={ props.on_discard.clone() }>{"Discard"}</button>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, locations::Location, travel::TravelModel};
+
+    fn at(x: f32) -> Location {
+        Location { lat: 0.0, lon: x, matrix_idx: None }
+    }
+
+    // one run along a line from a depot at 0 to one at 50, visiting the jobs in a zigzag
+    fn zigzag() -> AppState {
+        let mut state = new_state();
+        let mut run = Run::new("1".to_string(), "#ff0000", at(0.0), 0, 1000);
+        run.end_depot = at(50.0);
+        run.jobs = [30.0, 10.0, 40.0, 20.0].iter()
+            .map(|x| Job::new(format!("{x}"), "#ff0000", at(*x), 0))
+            .collect();
+        state.runs = vec![run];
+        state.unassigned.clear();
+        state.travel_model = TravelModel::Euclidean;
+        state.reschedule();
+        state
+    }
+
+    fn uids(runs: &[Run]) -> Vec<String> {
+        runs[0].jobs.iter().map(|job| job.uid.clone()).collect()
+    }
+
+    #[test]
+    fn straightens_a_run() {
+        let proposal = propose(&zigzag(), None);
+        assert_eq!(uids(&proposal.runs), ["10", "20", "30", "40"]);
+        assert_eq!((proposal.before.travel, proposal.after.travel), (130.0, 50.0));
+    }
+
+    #[test]
+    fn keeps_locked_jobs_in_place() {
+        let mut state = zigzag();
+        state.runs[0].jobs[0].locked = true;
+        let runs = optimize(&state, Some(0));
+        assert_eq!(uids(&runs)[0], "30");
+        assert!(locks_kept(&state.runs[0].jobs, &runs[0].jobs));
+    }
+
+    #[test]
+    fn locks_kept_checks_places() {
+        let state = zigzag();
+        let mut before = state.runs[0].jobs.clone();
+        before[1].locked = true;
+        let mut after = before.clone();
+        after.swap(2, 3);
+        assert!(locks_kept(&before, &after));
+        after.swap(0, 1);
+        assert!(!locks_kept(&before, &after));
+        assert!(!locks_kept(&before, &after[..1]));
+    }
+
+    #[test]
+    fn shift_overrun_is_not_lateness() {
+        let mut state = zigzag();
+        state.runs[0].end_time = 100;
+        state.reschedule();
+        let cost = plan_cost(&state.runs, provider(&state.travel_model, None).as_ref());
+        assert_eq!((cost.lateness, cost.overrun), (0.0, 30.0));
+        assert_eq!(cost.describe(), "130 min driving, 30 min over shift");
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, Job, RunIdx};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PairRole {
+    Pickup,
+    Delivery,
+}
+
+// Links a pickup to its delivery (and back). Both jobs have to be on the same run,
+// with the pickup first.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pair {
+    pub role: PairRole,
+    pub partner: String,
+}
+
+impl Pair {
+    pub fn describe(&self) -> String {
+        match self.role {
+            PairRole::Pickup => format!("pickup for {}", self.partner),
+            PairRole::Delivery => format!("delivery from {}", self.partner),
+        }
+    }
+
+    pub fn marker(&self) -> &'static str {
+        match self.role {
+            PairRole::Pickup => "↑",
+            PairRole::Delivery => "↓",
+        }
+    }
+}
+
+pub fn in_order(jobs: &[Job]) -> bool {
+    // every pickup comes before its delivery, where both are in `jobs`
+    jobs.iter().enumerate().all(|(seq, job)| {
+        let Some(pair) = &job.pair else { return true };
+        let partner_seq = jobs.iter().position(|other| other.uid == pair.partner);
+        match (partner_seq, pair.role) {
+            (Some(partner_seq), PairRole::Pickup) => seq < partner_seq,
+            (Some(partner_seq), PairRole::Delivery) => partner_seq < seq,
+            (None, _) => true,
+        }
+    })
+}
+
+// Somewhere jobs are laid out in lanes: the plan itself, or a move being tried out on a copy of some of its lanes.
+pub trait Lanes {
+    fn lane(&self, run_idx: RunIdx) -> Option<&Vec<Job>>;
+    fn lane_mut(&mut self, run_idx: RunIdx) -> Option<&mut Vec<Job>>;
+    fn find_job(&self, uid: &str) -> Option<(RunIdx, usize)>;
+}
+
+impl Lanes for AppState {
+    fn lane(&self, run_idx: RunIdx) -> Option<&Vec<Job>> {
+        AppState::lane(self, run_idx)
+    }
+
+    fn lane_mut(&mut self, run_idx: RunIdx) -> Option<&mut Vec<Job>> {
+        AppState::lane_mut(self, run_idx)
+    }
+
+    fn find_job(&self, uid: &str) -> Option<(RunIdx, usize)> {
+        AppState::find_job(self, uid)
+    }
+}
+
+pub fn lane_ok(jobs: &[Job], state: &AppState) -> bool {
+    // a partner on another run is a problem, one that doesn't exist at all is not.
+    let partners_here = jobs.iter().all(|job| {
+        let Some(pair) = &job.pair else { return true };
+        jobs.iter().any(|other| other.uid == pair.partner) || state.find_job(&pair.partner).is_none()
+    });
+    partners_here && in_order(jobs)
+}
+
+pub fn partner_locked_elsewhere(uid: &str, run_idx: RunIdx, lanes: &impl Lanes) -> bool {
+    // whether job `uid` has a locked partner on another lane, which carry_partner won't move
+    let Some(pair) = lanes.find_job(uid).and_then(|(lane, seq)| lanes.lane(lane)?.get(seq)?.pair.clone()) else { return false };
+    lanes.find_job(&pair.partner).is_some_and(|(partner_run_idx, partner_seq)| {
+        partner_run_idx != run_idx && lanes.lane(partner_run_idx).is_some_and(|jobs| jobs[partner_seq].locked)
+    })
+}
+
+pub fn carry_partner(uid: &str, run_idx: RunIdx, lanes: &mut impl Lanes) -> Option<()> {
+    // Moves the partner of job `uid` onto lane `run_idx`, next to it, if it is somewhere else.
+    let seq = lanes.lane(run_idx)?.iter().position(|job| job.uid == uid)?;
+    let pair = lanes.lane(run_idx)?.get(seq)?.pair.clone()?;
+    let (partner_run_idx, partner_seq) = lanes.find_job(&pair.partner)?;
+    if partner_run_idx == run_idx { return Some(()) };
+    // a locked partner stays put, which leaves the pair split for lane_ok to refuse
+    if lanes.lane(partner_run_idx)?.get(partner_seq)?.locked { return None };
+
+    let partner = lanes.lane_mut(partner_run_idx)?.remove(partner_seq);
+    let insert_idx = match pair.role {
+        PairRole::Pickup => seq + 1,
+        PairRole::Delivery => seq,
+    };
+    lanes.lane_mut(run_idx)?.insert(insert_idx, partner);
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, POOL_IDX, testing::uids};
+
+    // the demo plan's pool starts with pickup 12 and its delivery 13
+    fn pair(state: &AppState) -> Vec<Job> {
+        state.unassigned[..2].to_vec()
+    }
+
+    #[test]
+    fn pickups_come_first() {
+        let state = new_state();
+        let [pickup, delivery] = <[Job; 2]>::try_from(pair(&state)).unwrap();
+        let plain = state.unassigned[2].clone();
+        assert!(in_order(&[pickup.clone(), plain.clone(), delivery.clone()]));
+        assert!(!in_order(&[delivery.clone(), pickup.clone()]));
+        // a partner somewhere else is not a question of order
+        assert!(in_order(&[delivery, plain]));
+    }
+
+    #[test]
+    fn split_pairs_are_not_ok() {
+        let state = new_state();
+        let [pickup, delivery] = <[Job; 2]>::try_from(pair(&state)).unwrap();
+        assert!(lane_ok(&[pickup.clone(), delivery], &state));
+        assert!(!lane_ok(&[pickup], &state));
+    }
+
+    #[test]
+    fn carries_a_partner_across_runs() {
+        let mut state = new_state();
+        let pickup = state.unassigned.remove(0);
+        state.runs[1].jobs.insert(1, pickup);
+        assert_eq!(carry_partner("12", 1, &mut state), Some(()));
+        assert_eq!(&uids(&state, 1)[..4], ["4", "12", "13", "5"]);
+        assert_eq!(uids(&state, POOL_IDX), ["14"]);
+    }
+
+    #[test]
+    fn puts_a_carried_pickup_first() {
+        let mut state = new_state();
+        let delivery = state.unassigned.remove(1);
+        state.runs[1].jobs.insert(1, delivery);
+        assert_eq!(carry_partner("13", 1, &mut state), Some(()));
+        assert_eq!(&uids(&state, 1)[..3], ["4", "12", "13"]);
+    }
+
+    #[test]
+    fn leaves_a_locked_partner() {
+        let mut state = new_state();
+        state.unassigned[1].locked = true;
+        let pickup = state.unassigned.remove(0);
+        state.runs[1].jobs.insert(0, pickup);
+        assert!(partner_locked_elsewhere("12", 1, &state));
+        assert_eq!(carry_partner("12", 1, &mut state), None);
+        assert_eq!(uids(&state, POOL_IDX), ["13", "14"]);
+    }
+}
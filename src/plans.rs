@@ -0,0 +1,170 @@
+use gloo_storage::{LocalStorage, Storage, errors::StorageError};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::storage::STATE_KEY;
+
+// the list of saved plans, and which one is open
+pub const PLANS_KEY: &str = "timeline_plans";
+const DEFAULT_PLAN: &str = "default";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlanIndex {
+    pub names: Vec<String>,
+    pub current: String,
+}
+
+pub enum PlanAction {
+    Open(String),
+    New(String),
+    SaveAs(String),
+    Rename(String),
+    Delete,
+}
+
+pub fn plan_key(name: &str) -> String {
+    format!("{STATE_KEY}/{name}")
+}
+
+fn move_saved(from_key: &str, to_key: &str) {
+    if let Ok(saved) = LocalStorage::get::<Value>(from_key) {
+        if LocalStorage::set(to_key, saved).is_ok() {
+            LocalStorage::delete(from_key);
+        }
+    }
+}
+
+fn copy_saved(from_key: &str, to_key: &str) -> Result<(), String> {
+    let saved = LocalStorage::get::<Value>(from_key).map_err(|err| err.to_string())?;
+    LocalStorage::set(to_key, saved).map_err(|err| err.to_string())
+}
+
+impl PlanIndex {
+    pub fn load() -> (Self, Option<String>) {
+        // If the index cannot be read, it is copied to a backup key (as `storage::load` does
+        // for plans) and rebuilt from the plans that are saved, so none of them are lost.
+        let status = match LocalStorage::get::<Value>(PLANS_KEY) {
+            Ok(saved) => match serde_json::from_value(saved.clone()) {
+                Ok(index) => return (index, None),
+                Err(err) => {
+                    let backup_key = format!("{PLANS_KEY}_backup");
+                    let kept = LocalStorage::set(&backup_key, &saved).is_ok();
+                    let kept = if kept { format!(" It has been kept under \"{backup_key}\".") } else { String::new() };
+                    Some(format!("Could not read the list of plans: {err}.{kept}"))
+                }
+            },
+            Err(StorageError::KeyNotFound(_)) => None,
+            Err(err) => Some(format!("Could not read the list of plans: {err}")),
+        };
+        // Before there were named plans, the one plan was saved under STATE_KEY.
+        move_saved(STATE_KEY, &plan_key(DEFAULT_PLAN));
+        let keys = LocalStorage::get_all::<Map<String, Value>>().unwrap_or_default();
+        let index = Self::from_keys(keys.keys().map(String::as_str));
+        index.save();
+        (index, status)
+    }
+
+    fn from_keys<'a>(keys: impl Iterator<Item = &'a str>) -> Self {
+        // the plans saved under these storage keys, leaving out backups, with the first one open
+        let prefix = plan_key("");
+        let mut names: Vec<String> = keys
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .filter(|name| !name.ends_with("_backup"))
+            .map(str::to_string)
+            .collect();
+        if names.is_empty() {
+            names.push(DEFAULT_PLAN.to_string());
+        }
+        Self { current: names[0].clone(), names }
+    }
+
+    fn save(&self) {
+        // losing the index only loses the list, the plans themselves are still saved.
+        let _ = LocalStorage::set(PLANS_KEY, self);
+    }
+
+    pub fn current_key(&self) -> String {
+        plan_key(&self.current)
+    }
+
+    fn check_new_name(&self, name: &str) -> Result<(), String> {
+        if name.trim().is_empty() {
+            return Err("Give the plan a name first".to_string());
+        }
+        if self.names.iter().any(|existing| existing == name) {
+            return Err(format!("There is already a plan called \"{name}\""));
+        }
+        Ok(())
+    }
+
+    pub fn open(&mut self, name: &str) -> Result<(), String> {
+        if !self.names.iter().any(|existing| existing == name) {
+            return Err(format!("There is no plan called \"{name}\""));
+        }
+        self.current = name.to_string();
+        self.save();
+        Ok(())
+    }
+
+    pub fn create(&mut self, name: &str) -> Result<(), String> {
+        self.check_new_name(name)?;
+        self.names.push(name.to_string());
+        self.current = name.to_string();
+        self.save();
+        Ok(())
+    }
+
+    pub fn duplicate(&mut self, name: &str) -> Result<(), String> {
+        // copies the open plan (which should already be saved) and opens the copy
+        self.check_new_name(name)?;
+        copy_saved(&self.current_key(), &plan_key(name))
+            .map_err(|err| format!("Could not copy the plan: {err}"))?;
+        self.create(name)
+    }
+
+    pub fn rename(&mut self, name: &str) -> Result<(), String> {
+        self.check_new_name(name)?;
+        move_saved(&self.current_key(), &plan_key(name));
+        for existing in self.names.iter_mut().filter(|existing| **existing == self.current) {
+            *existing = name.to_string();
+        }
+        self.current = name.to_string();
+        self.save();
+        Ok(())
+    }
+
+    pub fn delete_current(&mut self) {
+        // opens the next plan along, or a fresh default plan if this was the last one.
+        LocalStorage::delete(self.current_key());
+        self.names.retain(|existing| *existing != self.current);
+        if self.names.is_empty() {
+            self.names.push(DEFAULT_PLAN.to_string());
+        }
+        self.current = self.names[0].clone();
+        self.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(names: &[&str]) -> PlanIndex {
+        PlanIndex { names: names.iter().map(|name| name.to_string()).collect(), current: names[0].to_string() }
+    }
+
+    #[test]
+    fn new_names_must_be_given_and_unused() {
+        let plans = index(&["default", "monday"]);
+        assert_eq!(plans.check_new_name("tuesday"), Ok(()));
+        assert_eq!(plans.check_new_name("  "), Err("Give the plan a name first".to_string()));
+        assert_eq!(plans.check_new_name("monday"), Err("There is already a plan called \"monday\"".to_string()));
+    }
+
+    #[test]
+    fn rebuilds_the_index_from_saved_plans() {
+        let keys = [plan_key("default"), plan_key("monday"), plan_key("monday_backup"), PLANS_KEY.to_string()];
+        assert_eq!(PlanIndex::from_keys(keys.iter().map(String::as_str)), index(&["default", "monday"]));
+        assert_eq!(PlanIndex::from_keys([PLANS_KEY].into_iter()), index(&["default"]));
+    }
+}
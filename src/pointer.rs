@@ -0,0 +1,148 @@
+// Dragging with touch or a pen, where HTML5 drag and drop does nothing. Once a press has moved
+// far enough it follows the pointer over the whole window with a ghost of the dragged jobs, hit-tests
+// the leg or job under it, and sends the same messages as a drag.
+use std::{cell::RefCell, rc::Rc};
+
+use gloo_events::EventListener;
+use gloo_utils::{document, window};
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, PointerEvent};
+use yew::html::Scope;
+
+use crate::{App, Msg, Position, animations::get_item_at_pos, line_components::parse_item_id};
+
+// how far the pointer moves (in px) before a press becomes a drag, so that taps still select
+const DRAG_THRESHOLD_PX: f64 = 8.0;
+
+pub fn uses_pointer_drag(event: &PointerEvent) -> bool {
+    // mice have HTML5 drag and drop
+    event.pointer_type() != "mouse"
+}
+
+struct Progress {
+    from_pos: Position,
+    // every job that moves with it, for the ghost
+    block: Vec<Position>,
+    start: (f64, f64),
+    ghost: Option<HtmlElement>,
+    over: Option<Position>,
+}
+
+// Keeps the window listeners alive for as long as the drag lasts.
+pub struct PointerDrag {
+    _listeners: [EventListener; 3],
+}
+
+pub fn position_at(x: f64, y: f64) -> Option<Position> {
+    // the leg or job under the pointer, or under the element the pointer is over
+    let mut element: Element = document().element_from_point(x as f32, y as f32)?;
+    loop {
+        if let Some(pos) = parse_item_id(&element.id()) { return Some(pos) };
+        element = element.parent_element()?;
+    }
+}
+
+fn make_ghost(block: &[Position]) -> Option<HtmlElement> {
+    // copies of the dragged jobs, side by side, that follow the pointer
+    let ghost = document().create_element("div").ok()?.dyn_into::<HtmlElement>().ok()?;
+    ghost.set_class_name("drag-ghost");
+    for pos in block {
+        let job = get_item_at_pos(pos)?;
+        let copy = job.clone_node_with_deep(true).ok()?.dyn_into::<Element>().ok()?;
+        // the original keeps its id, so that it can still be found by position
+        copy.remove_attribute("id").ok()?;
+        ghost.append_child(&copy).ok()?;
+    }
+    document().body()?.append_child(&ghost).ok()?;
+    Some(ghost)
+}
+
+fn move_ghost(ghost: &HtmlElement, x: f64, y: f64) {
+    let style = ghost.style();
+    let _ = style.set_property("left", &format!("{x}px"));
+    let _ = style.set_property("top", &format!("{y}px"));
+}
+
+impl Progress {
+    fn started(&self) -> bool {
+        self.ghost.is_some()
+    }
+
+    fn finish(&mut self, link: &Scope<App>, dropped: bool) {
+        if let Some(ghost) = self.ghost.take() {
+            ghost.remove();
+            let msg = match self.over {
+                // dropping a job on itself leaves it where it is
+                Some(pos) if dropped && pos != self.from_pos => Msg::Drop(pos),
+                _ => Msg::DragEnd,
+            };
+            link.send_message(msg);
+        }
+        link.send_message(Msg::PointerDone);
+    }
+}
+
+impl PointerDrag {
+    pub fn start(from_pos: Position, block: Vec<Position>, event: &PointerEvent, link: Scope<App>) -> Self {
+        let progress = Rc::new(RefCell::new(Progress {
+            from_pos,
+            block,
+            start: (event.client_x() as f64, event.client_y() as f64),
+            ghost: None,
+            over: None,
+        }));
+        let pointer_id = event.pointer_id();
+
+        let on_move = {
+            let progress = progress.clone();
+            let link = link.clone();
+            EventListener::new(&window(), "pointermove", move |event| {
+                let Some(event) = event.dyn_ref::<PointerEvent>() else { return };
+                if event.pointer_id() != pointer_id { return };
+                let (x, y) = (event.client_x() as f64, event.client_y() as f64);
+                let mut progress = progress.borrow_mut();
+
+                if !progress.started() {
+                    let (start_x, start_y) = progress.start;
+                    if (x - start_x).hypot(y - start_y) < DRAG_THRESHOLD_PX { return };
+                    // the ghost is copied before DragStart hides the job
+                    progress.ghost = make_ghost(&progress.block);
+                    if !progress.started() { return };
+                    link.send_message(Msg::DragStart(progress.from_pos));
+                    link.send_message(Msg::StartAutoScroll);
+                }
+
+                if let Some(ghost) = &progress.ghost {
+                    move_ghost(ghost, x, y);
+                }
+                // auto-scrolling can also change what is under the pointer, so the app
+                // keeps track of where the drag is and ignores repeats
+                let Some(over) = position_at(x, y) else { return };
+                progress.over = Some(over);
+                link.send_message(Msg::DragEnter(over));
+            })
+        };
+
+        let on_up = {
+            let progress = progress.clone();
+            let link = link.clone();
+            EventListener::new(&window(), "pointerup", move |event| {
+                let Some(event) = event.dyn_ref::<PointerEvent>() else { return };
+                if event.pointer_id() != pointer_id { return };
+                let mut progress = progress.borrow_mut();
+                // the timeline may have scrolled since the pointer last moved
+                let (x, y) = (event.client_x() as f64, event.client_y() as f64);
+                progress.over = position_at(x, y).or(progress.over);
+                progress.finish(&link, true);
+            })
+        };
+
+        let on_cancel = EventListener::new(&window(), "pointercancel", move |event| {
+            let Some(event) = event.dyn_ref::<PointerEvent>() else { return };
+            if event.pointer_id() != pointer_id { return };
+            progress.borrow_mut().finish(&link, false);
+        });
+
+        Self { _listeners: [on_move, on_up, on_cancel] }
+    }
+}
@@ -0,0 +1,40 @@
+use yew::prelude::*;
+
+use crate::{Minutes, schedule::format_time, line_components::px};
+
+#[derive(Properties, PartialEq)]
+pub struct RulerProps {
+    pub start_time: Minutes,
+    pub end_time: Minutes,
+    pub px_per_minute: f32,
+}
+
+const HALF_HOUR: Minutes = 30;
+
+fn render_tick(time: Minutes, props: &RulerProps) -> Html {
+    let left = px((time - props.start_time) as f32, props.px_per_minute);
+    let on_the_hour = time.is_multiple_of(60);
+    let class = if on_the_hour { "tick hour" } else { "tick half-hour" };
+
+    html! {
+        <div class={ class } style={ format!("left: {left}px") }>
+            if on_the_hour {
+                <span class="tick-label">{ format_time(time) }</span>
+            }
+        </div>
+    }
+}
+
+#[function_component(RulerComponent)]
+pub fn ruler(props: &RulerProps) -> Html {
+    // ticks fall on the half hour, so the first one may be a little after start_time.
+    let first_tick = props.start_time.div_ceil(HALF_HOUR) * HALF_HOUR;
+    let ticks = (first_tick..=props.end_time).step_by(HALF_HOUR);
+    let width = px((props.end_time - props.start_time) as f32, props.px_per_minute);
+
+    html! {
+        <div class="ruler" style={ format!("width: {width}px") }>
+            { for ticks.map(|time| render_tick(time, props)) }
+        </div>
+    }
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Run, Minutes, travel::{TravelTimeProvider, to_minutes}};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobTimes {
+    pub arrival: Minutes,
+    pub start: Minutes,
+    pub departure: Minutes,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub earliest: Option<Minutes>,
+    pub latest: Option<Minutes>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Violation {
+    Early(Minutes),
+    Late(Minutes),
+    // would split a pickup from its delivery, or put the delivery first
+    Precedence,
+    // would move a locked job from its place
+    Locked,
+}
+
+impl TimeWindow {
+    pub fn new(earliest: Minutes, latest: Minutes) -> Self {
+        Self { earliest: Some(earliest), latest: Some(latest) }
+    }
+
+    pub fn check(&self, arrival: Minutes) -> Option<Violation> {
+        if let Some(earliest) = self.earliest {
+            if arrival < earliest { return Some(Violation::Early(earliest - arrival)) }
+        }
+        if let Some(latest) = self.latest {
+            if arrival > latest { return Some(Violation::Late(arrival - latest)) }
+        }
+        None
+    }
+}
+
+impl Violation {
+    pub fn class(&self) -> &'static str {
+        match self {
+            Self::Early(_) => " early",
+            Self::Late(_) => " late",
+            Self::Precedence | Self::Locked => " invalid",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Early(minutes) => format!("{minutes} min early"),
+            Self::Late(minutes) => format!("{minutes} min late"),
+            Self::Precedence => "a pickup must come before its delivery, on the same run".to_string(),
+            Self::Locked => "a locked job would be moved from its place".to_string(),
+        }
+    }
+}
+
+pub fn schedule_run(run: &mut Run, travel: &dyn TravelTimeProvider) {
+    // walk the run from the start depot, adding each leg and each service duration in turn.
+    // The clock keeps fractions of a minute, and only the times shown are rounded,
+    // so that rounding does not add up along the run.
+    let mut clock = run.start_time as f32;
+    let mut here = run.start_depot.clone();
+
+    for job in run.jobs.iter_mut() {
+        clock += travel.travel_time(&here, &job.location);
+        here = job.location.clone();
+
+        let arrival = to_minutes(clock);
+        // an early arrival waits for the window to open.
+        clock = clock.max(job.window.earliest.map_or(clock, |earliest| earliest as f32));
+        let start = to_minutes(clock);
+        clock += job.service_minutes as f32;
+        job.times = JobTimes { arrival, start, departure: to_minutes(clock) };
+        job.preview_times = None;
+    }
+
+    run.return_time = to_minutes(clock + travel.travel_time(&here, &run.end_depot));
+}
+
+pub fn travel_minutes(run: &Run, travel: &dyn TravelTimeProvider) -> f32 {
+    // the time spent driving, from the start depot to the end depot
+    let stops = run.jobs.iter().map(|job| &job.location);
+    let (total, last) = stops.fold((0.0, &run.start_depot), |(total, here), next| {
+        (total + travel.travel_time(here, next), next)
+    });
+    total + travel.travel_time(last, &run.end_depot)
+}
+
+pub fn lateness(run: &Run) -> f32 {
+    // minutes late at each job
+    run.jobs.iter()
+        .filter_map(|job| match job.window.check(job.times.arrival) {
+            Some(Violation::Late(minutes)) => Some(minutes as f32),
+            _ => None,
+        })
+        .sum()
+}
+
+pub fn overrun(run: &Run) -> f32 {
+    // minutes back at the end depot after the end of the shift
+    run.return_time.saturating_sub(run.end_time) as f32
+}
+
+pub fn schedule_runs(runs: &mut [Run], travel: &dyn TravelTimeProvider) {
+    for run in runs.iter_mut() {
+        schedule_run(run, travel);
+    }
+}
+
+pub fn format_time(minutes: Minutes) -> String {
+    format!("{:02}:{:02}", (minutes / 60) % 24, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Job, locations::Location, travel::Euclidean};
+
+    fn at(x: f32) -> Location {
+        Location { lat: 0.0, lon: x, matrix_idx: None }
+    }
+
+    fn run_to(stops: &[(f32, TimeWindow, Minutes)], start_time: Minutes, end_time: Minutes) -> Run {
+        // a run from a depot at 0, and back, at one unit a minute
+        let mut run = Run::new("1".to_string(), "#ff0000", at(0.0), start_time, end_time);
+        run.jobs = stops.iter().enumerate()
+            .map(|(i, (x, window, service_minutes))| {
+                let mut job = Job::new(i.to_string(), "#ff0000", at(*x), *service_minutes);
+                job.window = *window;
+                job
+            })
+            .collect();
+        schedule_run(&mut run, &Euclidean { speed: 1.0 });
+        run
+    }
+
+    #[test]
+    fn checks_both_ends_of_a_window() {
+        let window = TimeWindow::new(500, 600);
+        assert_eq!(window.check(490), Some(Violation::Early(10)));
+        assert_eq!(window.check(500), None);
+        assert_eq!(window.check(600), None);
+        assert_eq!(window.check(615), Some(Violation::Late(15)));
+        assert_eq!(TimeWindow::default().check(0), None);
+    }
+
+    #[test]
+    fn waits_for_the_window_to_open() {
+        let run = run_to(&[(10.0, TimeWindow::new(500, 600), 5)], 480, 600);
+        assert_eq!(run.jobs[0].times, JobTimes { arrival: 490, start: 500, departure: 505 });
+        assert_eq!(run.return_time, 515);
+        assert_eq!((lateness(&run), overrun(&run)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn measures_lateness_and_overrun() {
+        let late = TimeWindow { earliest: None, latest: Some(485) };
+        let run = run_to(&[(10.0, late, 20)], 480, 500);
+        assert_eq!(run.jobs[0].times, JobTimes { arrival: 490, start: 490, departure: 510 });
+        assert_eq!(run.return_time, 520);
+        assert_eq!((lateness(&run), overrun(&run)), (5.0, 20.0));
+    }
+
+    #[test]
+    fn rounds_each_stop_not_each_leg() {
+        // legs of 0.4 minutes would all round to nothing
+        let stops: Vec<_> = [0.4, 0.8, 1.2, 1.6, 2.0].iter().map(|x| (*x, TimeWindow::default(), 0)).collect();
+        let run = run_to(&stops, 0, 100);
+        let arrivals: Vec<Minutes> = run.jobs.iter().map(|job| job.times.arrival).collect();
+        assert_eq!(arrivals, [0, 1, 1, 2, 2]);
+        assert_eq!(run.return_time, 4);
+    }
+}
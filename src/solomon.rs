@@ -0,0 +1,172 @@
+// Reads the Solomon and Gehring-Homberger VRPTW benchmark instances, and their best known
+// solutions. Coordinates are plain x/y, and travel time is the Euclidean distance.
+use crate::{AppState, Job, Run, Minutes};
+use crate::{locations::Location, capacity::Load, schedule::TimeWindow, travel::TravelModel};
+use crate::colors::{get_cycled_color, UNASSIGNED_COLOR};
+
+// one customer row: number, x, y, demand, ready time, due date, service time
+const CUSTOMER_COLUMNS: usize = 7;
+
+struct Customer {
+    number: usize,
+    location: Location,
+    demand: f32,
+    ready: Minutes,
+    due: Minutes,
+    service: Minutes,
+}
+
+fn numbers(line: &str) -> Option<Vec<f32>> {
+    line.split_whitespace().map(|field| field.parse().ok()).collect()
+}
+
+fn customer(fields: &[f32]) -> Customer {
+    let minutes = |value: f32| value.round() as Minutes;
+    Customer {
+        number: fields[0] as usize,
+        location: Location { lat: fields[2], lon: fields[1], matrix_idx: None },
+        demand: fields[3],
+        ready: minutes(fields[4]),
+        due: minutes(fields[5]),
+        service: minutes(fields[6]),
+    }
+}
+
+pub fn import_instance(text: &str, state: &mut AppState) -> Result<String, String> {
+    // Replaces the plan with the instance's fleet of empty runs, and every customer unassigned.
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let name = lines.next().unwrap_or_default().to_string();
+
+    let mut fleet = None;
+    let mut customers = vec![];
+    for line in lines {
+        let Some(fields) = numbers(line) else { continue };
+        match (fleet, fields.len()) {
+            (None, 2) => fleet = Some((fields[0] as usize, fields[1])),
+            (Some(_), CUSTOMER_COLUMNS) => customers.push(customer(&fields)),
+            _ => return Err(format!("Not a Solomon instance, did not expect the line \"{line}\"")),
+        }
+    }
+    let Some((n_vehicles, capacity)) = fleet else {
+        return Err("Not a Solomon instance, it has no vehicle section".to_string())
+    };
+    if customers.is_empty() {
+        return Err("Not a Solomon instance, it has no customers".to_string())
+    }
+
+    // the first customer is the depot, and its time window is the working day
+    let depot = customers.remove(0);
+    state.runs = (0..n_vehicles).map(|i| {
+        let color = get_cycled_color(i, n_vehicles);
+        let mut run = Run::new((i + 1).to_string(), &color, depot.location.clone(), depot.ready, depot.due);
+        run.capacity = Some(Load(vec![capacity]));
+        run
+    }).collect();
+
+    let n_customers = customers.len();
+    let jobs = customers.into_iter().map(|customer| {
+        let mut job = Job::new(customer.number.to_string(), UNASSIGNED_COLOR, customer.location, customer.service);
+        job.window = TimeWindow::new(customer.ready, customer.due);
+        job.demand = Load(vec![customer.demand]);
+        (job.uid.clone(), job)
+    }).collect();
+    state.unassigned.clear();
+    state.unassign(jobs);
+    state.travel_model = TravelModel::Euclidean;
+    state.travel_matrix = None;
+    state.reschedule();
+
+    Ok(format!("Loaded {name} with {n_vehicles} vehicles and {n_customers} customers"))
+}
+
+pub fn import_solution(text: &str, state: &mut AppState) -> Result<String, String> {
+    // Lays out the current plan's customers along the "Route N : 1 2 3" lines of a solution.
+    let routes: Vec<Vec<&str>> = text.lines()
+        .filter_map(|line| line.trim().strip_prefix("Route"))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(_, customers)| customers.split_whitespace().collect())
+        .collect();
+    if routes.is_empty() {
+        return Err("Not a Solomon solution, it has no \"Route\" lines".to_string())
+    }
+    if routes.len() > state.runs.len() {
+        return Err(format!("The solution has {} routes, but the plan only has {} runs", routes.len(), state.runs.len()))
+    }
+
+    let mut jobs = state.take_jobs();
+    let mut missing = vec![];
+    for (run, route) in state.runs.iter_mut().zip(routes.iter()) {
+        for uid in route {
+            match jobs.remove(*uid) {
+                Some(mut job) => {
+                    job.color = run.color.clone();
+                    run.jobs.push(job);
+                }
+                None => missing.push(uid.to_string()),
+            }
+        }
+    }
+    state.unassign(jobs);
+    state.reschedule();
+
+    let loaded = format!("Loaded {} routes", routes.len());
+    match missing.is_empty() {
+        true => Ok(loaded),
+        false => Ok(format!("{loaded}, but the plan has no customers {}", missing.join(", "))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_state, schedule::lateness};
+
+    // C101's layout, cut down to three customers a sqrt(13) drive apart, the last of which
+    // is only reached in time if the legs are not rounded one by one
+    const INSTANCE: &str = "C101
+
+VEHICLE
+NUMBER     CAPACITY
+   2         200
+
+CUSTOMER
+CUST NO.  XCOORD.   YCOORD.    DEMAND   READY TIME  DUE DATE   SERVICE   TIME
+
+    0       0          0          0          0        100          0
+    1       2          3         10          0        100          0
+    2       4          6         30          0        100          0
+    3       6          9         10          0         11          0
+";
+
+    const SOLUTION: &str = "Instance name : C101
+Authors       : Nobody
+Date          : 2024
+Reference     : None
+Solution
+Route 1 : 1 2 3
+";
+
+    #[test]
+    fn reads_an_instance() {
+        let mut state = new_state();
+        let loaded = import_instance(INSTANCE, &mut state).unwrap();
+        assert_eq!(loaded, "Loaded C101 with 2 vehicles and 3 customers");
+        assert_eq!(state.runs.len(), 2);
+        assert_eq!(state.runs[0].capacity, Some(Load(vec![200.0])));
+        assert_eq!((state.runs[0].start_time, state.runs[0].end_time), (0, 100));
+        assert_eq!(state.unassigned.iter().map(|job| job.uid.as_str()).collect::<Vec<_>>(), ["1", "2", "3"]);
+        assert_eq!(state.unassigned[2].window, TimeWindow::new(0, 11));
+        assert_eq!(state.unassigned[1].demand, Load(vec![30.0]));
+    }
+
+    #[test]
+    fn best_known_routes_are_on_time() {
+        let mut state = new_state();
+        import_instance(INSTANCE, &mut state).unwrap();
+        assert_eq!(import_solution(SOLUTION, &mut state), Ok("Loaded 1 routes".to_string()));
+        let run = &state.runs[0];
+        assert_eq!(run.jobs.iter().map(|job| job.times.arrival).collect::<Vec<_>>(), [4, 7, 11]);
+        assert_eq!(lateness(run), 0.0);
+        assert!(state.unassigned.is_empty());
+    }
+}
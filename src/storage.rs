@@ -0,0 +1,142 @@
+use gloo_storage::{LocalStorage, Storage, errors::StorageError};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{AppState, animation_strategy::Strategy};
+
+pub const STATE_KEY: &str = "timeline_state";
+
+// Version 1 is anything saved before versioning: either a bare list of runs,
+// or a bare AppState, possibly from before jobs had service times and runs had depots.
+// Version 2 wraps the AppState in an Envelope.
+pub const SCHEMA_VERSION: u32 = 2;
+
+// used for jobs saved before they had a service time
+const LEGACY_SERVICE_MINUTES: u64 = 10;
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    state: Value,
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    version: u32,
+    state: &'a AppState,
+}
+
+fn migrate_v1(legacy: Value) -> Value {
+    let mut state = match legacy {
+        Value::Array(runs) => json!({ "runs": runs, "animation_strategy": Strategy::None }),
+        other => other,
+    };
+
+    let runs = state.get_mut("runs").and_then(Value::as_array_mut);
+    for run in runs.into_iter().flatten() {
+        let jobs = run.get_mut("jobs").and_then(Value::as_array_mut);
+        let mut first_location = None;
+        for job in jobs.into_iter().flatten() {
+            if job.get("service_minutes").is_none() {
+                job["service_minutes"] = json!(LEGACY_SERVICE_MINUTES);
+            }
+            first_location = first_location.or_else(|| job.get("location").cloned());
+        }
+        // the depots were not saved, so start and end at the first job
+        let depot = first_location.unwrap_or(json!({ "lat": 0.0, "lon": 0.0 }));
+        for key in ["start_depot", "end_depot"] {
+            if run.get(key).is_none() {
+                run[key] = depot.clone();
+            }
+        }
+    }
+    state
+}
+
+fn migrate(version: u32, state: Value) -> Result<Value, String> {
+    // each step takes the state one version further
+    match version {
+        1 => migrate(2, migrate_v1(state)),
+        SCHEMA_VERSION => Ok(state),
+        newer => Err(format!("it was saved by a newer version of the app (schema v{newer})")),
+    }
+}
+
+fn parse(saved: Value) -> Result<AppState, String> {
+    let envelope = match serde_json::from_value::<Envelope>(saved.clone()) {
+        Ok(envelope) => envelope,
+        Err(_) => Envelope { version: 1, state: saved },
+    };
+    let state = migrate(envelope.version, envelope.state)?;
+    serde_json::from_value(state).map_err(|err| err.to_string())
+}
+
+pub fn load(key: &str) -> Result<Option<AppState>, String> {
+    // Ok(None) if nothing has been saved. If the saved plan cannot be loaded, it is
+    // copied to a backup key so it is not lost when the next plan is saved over it.
+    let saved: Value = match LocalStorage::get(key) {
+        Ok(saved) => saved,
+        Err(StorageError::KeyNotFound(_)) => return Ok(None),
+        Err(err) => return Err(format!("Could not read the saved plan: {err}")),
+    };
+
+    parse(saved.clone()).map(Some).map_err(|err| {
+        let backup_key = format!("{key}_backup");
+        let kept = LocalStorage::set(&backup_key, &saved).is_ok();
+        let kept = if kept { format!(" It has been kept under \"{backup_key}\".") } else { String::new() };
+        format!("Could not load the saved plan: {err}.{kept}")
+    })
+}
+
+pub fn save(key: &str, state: &AppState) -> Result<(), String> {
+    let envelope = EnvelopeRef { version: SCHEMA_VERSION, state };
+    LocalStorage::set(key, envelope).map_err(|err| format!("Could not save the plan: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a run as the app saved it before versioning: no service times, depots or windows
+    fn v1_runs() -> Value {
+        json!([{
+            "jobs": [
+                { "uid": "0", "color": "#ff0000", "location": { "lat": 0.25, "lon": 0.5 }, "pushed": false, "pull": false },
+                { "uid": "1", "color": "#ff0000", "location": { "lat": 0.75, "lon": 0.5 }, "pushed": false, "pull": false },
+            ],
+            "color": "#ff0000",
+            "start_time": 480,
+            "end_time": 660,
+        }])
+    }
+
+    fn round_trip(state: &AppState) -> AppState {
+        let saved = serde_json::to_value(EnvelopeRef { version: SCHEMA_VERSION, state }).unwrap();
+        parse(saved).unwrap()
+    }
+
+    #[test]
+    fn loads_v1_runs() {
+        let state = parse(v1_runs()).unwrap();
+        assert_eq!(state.animation_strategy, Strategy::None);
+        let run = &state.runs[0];
+        assert_eq!(run.jobs.len(), 2);
+        assert_eq!(run.jobs[0].service_minutes, LEGACY_SERVICE_MINUTES as usize);
+        assert_eq!(run.start_depot, run.jobs[0].location);
+        assert_eq!(round_trip(&state), state);
+    }
+
+    #[test]
+    fn loads_v1_state() {
+        let saved = json!({ "runs": v1_runs(), "animation_strategy": "ScaleAndSlide" });
+        let state = parse(saved).unwrap();
+        assert_eq!(state.animation_strategy, Strategy::ScaleAndSlide);
+        assert_eq!(round_trip(&state), state);
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        let saved = json!({ "version": SCHEMA_VERSION + 1, "state": {} });
+        assert!(parse(saved).is_err());
+    }
+}
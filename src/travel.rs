@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString, EnumVariantNames};
+
+use crate::{Minutes, locations::{Location, haversine_dist}};
+
+pub trait TravelTimeProvider {
+    // travel time in minutes
+    fn travel_time(&self, from: &Location, to: &Location) -> f32;
+    // in km, or in coordinate units for the planar providers
+    fn distance(&self, from: &Location, to: &Location) -> f32;
+}
+
+pub struct GreatCircle {
+    // km per minute
+    pub speed: f32,
+}
+
+impl TravelTimeProvider for GreatCircle {
+    fn travel_time(&self, from: &Location, to: &Location) -> f32 {
+        self.distance(from, to) / self.speed
+    }
+
+    fn distance(&self, from: &Location, to: &Location) -> f32 {
+        haversine_dist(from, to)
+    }
+}
+
+// Treats lat/lon as plain x/y coordinates, like the Solomon benchmark instances do.
+pub struct Euclidean {
+    // coordinate units per minute
+    pub speed: f32,
+}
+
+impl TravelTimeProvider for Euclidean {
+    fn travel_time(&self, from: &Location, to: &Location) -> f32 {
+        self.distance(from, to) / self.speed
+    }
+
+    fn distance(&self, from: &Location, to: &Location) -> f32 {
+        let dx = to.lon - from.lon;
+        let dy = to.lat - from.lat;
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+pub struct Manhattan {
+    // coordinate units per minute
+    pub speed: f32,
+}
+
+impl TravelTimeProvider for Manhattan {
+    fn travel_time(&self, from: &Location, to: &Location) -> f32 {
+        self.distance(from, to) / self.speed
+    }
+
+    fn distance(&self, from: &Location, to: &Location) -> f32 {
+        (to.lon - from.lon).abs() + (to.lat - from.lat).abs()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TravelMatrix {
+    // durations[from][to] in minutes, indexed by `Location::matrix_idx`,
+    // None where there is no route between the two
+    pub durations: Vec<Vec<Option<f32>>>,
+    // distances[from][to] in km
+    #[serde(default)]
+    pub distances: Vec<Vec<Option<f32>>>,
+    // the place each row stands for, where the matrix says, so that jobs can be matched to rows
+    #[serde(default)]
+    pub locations: Vec<Location>,
+}
+
+// Looks up precomputed durations, and falls back to another provider
+// for locations which are not in the matrix.
+pub struct Matrix<'a> {
+    pub matrix: &'a TravelMatrix,
+    pub fallback: GreatCircle,
+}
+
+fn lookup(table: &[Vec<Option<f32>>], from: &Location, to: &Location) -> Option<f32> {
+    let (from_idx, to_idx) = from.matrix_idx.zip(to.matrix_idx)?;
+    table.get(from_idx)?.get(to_idx).copied().flatten()
+}
+
+impl TravelTimeProvider for Matrix<'_> {
+    fn travel_time(&self, from: &Location, to: &Location) -> f32 {
+        lookup(&self.matrix.durations, from, to).unwrap_or_else(|| self.fallback.travel_time(from, to))
+    }
+
+    fn distance(&self, from: &Location, to: &Location) -> f32 {
+        lookup(&self.matrix.distances, from, to).unwrap_or_else(|| self.fallback.distance(from, to))
+    }
+}
+
+// about 40 km/h, a typical average for deliveries by road
+const GREAT_CIRCLE_KM_PER_MINUTE: f32 = 0.7;
+// coordinate units per minute
+const PLANAR_UNITS_PER_MINUTE: f32 = 1.0;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, EnumString, EnumVariantNames, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum TravelModel {
+    #[default]
+    GreatCircle,
+    Euclidean,
+    Manhattan,
+    Matrix,
+}
+
+pub fn provider<'a>(model: &TravelModel, matrix: Option<&'a TravelMatrix>) -> Box<dyn TravelTimeProvider + 'a> {
+    let great_circle = GreatCircle { speed: GREAT_CIRCLE_KM_PER_MINUTE };
+    match (model, matrix) {
+        (TravelModel::GreatCircle, _) => Box::new(great_circle),
+        (TravelModel::Euclidean, _) => Box::new(Euclidean { speed: PLANAR_UNITS_PER_MINUTE }),
+        (TravelModel::Manhattan, _) => Box::new(Manhattan { speed: PLANAR_UNITS_PER_MINUTE }),
+        (TravelModel::Matrix, Some(matrix)) => Box::new(Matrix { matrix, fallback: great_circle }),
+        // nothing has been loaded yet
+        (TravelModel::Matrix, None) => Box::new(great_circle),
+    }
+}
+
+pub fn to_minutes(duration: f32) -> Minutes {
+    duration.round() as Minutes
+}
@@ -0,0 +1,533 @@
+// Reads VROOM problems (vehicles, jobs and shipments) and solutions (routes),
+// and writes the plan back out as a VROOM solution.
+// VROOM works in seconds, and writes locations as [lon, lat].
+use std::{collections::HashMap, hash::Hash, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, Job, Run, Minutes, RunIdx, POOL_IDX};
+use crate::{locations::Location, capacity::{Load, load_profile}, pairs::{Pair, PairRole}};
+use crate::{schedule::TimeWindow, travel::{TravelMatrix, TravelModel, provider}};
+use crate::colors::{get_cycled_color, UNASSIGNED_COLOR};
+use crate::matrix::{per_minute, per_km};
+
+type Seconds = u64;
+
+fn to_minutes(seconds: Seconds) -> Minutes {
+    (seconds as f32 / 60.0).round() as Minutes
+}
+
+fn to_seconds(minutes: Minutes) -> Seconds {
+    minutes as Seconds * 60
+}
+
+#[derive(Deserialize)]
+struct Vehicle {
+    id: u64,
+    start: Option<[f32; 2]>,
+    start_index: Option<usize>,
+    end: Option<[f32; 2]>,
+    end_index: Option<usize>,
+    #[serde(default)]
+    capacity: Vec<f32>,
+    time_window: Option<[Seconds; 2]>,
+}
+
+#[derive(Deserialize)]
+struct VroomJob {
+    id: u64,
+    location: Option<[f32; 2]>,
+    location_index: Option<usize>,
+    #[serde(default)]
+    service: Seconds,
+    #[serde(default)]
+    delivery: Vec<f32>,
+    #[serde(default)]
+    pickup: Vec<f32>,
+    // older problems give a single amount instead of delivery/pickup
+    #[serde(default)]
+    amount: Vec<f32>,
+    #[serde(default)]
+    time_windows: Vec<[Seconds; 2]>,
+}
+
+#[derive(Deserialize)]
+struct Shipment {
+    pickup: VroomJob,
+    delivery: VroomJob,
+    #[serde(default)]
+    amount: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct ProfileMatrix {
+    durations: Vec<Vec<Option<f32>>>,
+    #[serde(default)]
+    distances: Vec<Vec<Option<f32>>>,
+}
+
+#[derive(Deserialize)]
+struct Problem {
+    vehicles: Vec<Vehicle>,
+    #[serde(default)]
+    jobs: Vec<VroomJob>,
+    #[serde(default)]
+    shipments: Vec<Shipment>,
+    // one matrix per routing profile
+    #[serde(default)]
+    matrices: HashMap<String, ProfileMatrix>,
+    // older problems give a single durations matrix
+    #[serde(default)]
+    matrix: Vec<Vec<Option<f32>>>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Step {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    // VROOM passes a task's description through to its steps. Exported steps give the job's uid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<[f32; 2]>,
+    #[serde(default)]
+    arrival: Seconds,
+    // travel time so far
+    #[serde(default)]
+    duration: Seconds,
+    #[serde(default)]
+    service: Seconds,
+    #[serde(default)]
+    waiting_time: Seconds,
+    #[serde(default)]
+    load: Vec<f32>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Route {
+    vehicle: u64,
+    steps: Vec<Step>,
+    #[serde(default)]
+    cost: Seconds,
+    #[serde(default)]
+    duration: Seconds,
+    #[serde(default)]
+    service: Seconds,
+    #[serde(default)]
+    waiting_time: Seconds,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Unassigned {
+    id: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    cost: Seconds,
+    routes: usize,
+    unassigned: usize,
+    duration: Seconds,
+    service: Seconds,
+    waiting_time: Seconds,
+}
+
+#[derive(Deserialize)]
+struct Solution {
+    routes: Vec<Route>,
+}
+
+#[derive(Serialize)]
+struct SolutionOut {
+    code: u8,
+    summary: Summary,
+    unassigned: Vec<Unassigned>,
+    routes: Vec<Route>,
+}
+
+// Job uids say what kind of VROOM task they came from, because
+// job, pickup and delivery ids do not have to be unique between kinds.
+fn uid(kind: &str, id: u64) -> String {
+    match kind {
+        "pickup" => format!("p{id}"),
+        "delivery" => format!("d{id}"),
+        _ => id.to_string(),
+    }
+}
+
+fn kind(job: &Job) -> &'static str {
+    match job.pair.as_ref().map(|pair| pair.role) {
+        Some(PairRole::Pickup) => "pickup",
+        Some(PairRole::Delivery) => "delivery",
+        None => "job",
+    }
+}
+
+fn fallback_ids<T: Hash + Eq>(parsed: Vec<(T, Option<u64>)>) -> HashMap<T, u64> {
+    // Things not from VROOM get made up ids, above every real one so they cannot clash.
+    let mut next_id = parsed.iter().filter_map(|(_, id)| *id).max().map_or(1, |id| id + 1);
+    parsed.into_iter().map(|(key, id)| {
+        let id = id.unwrap_or_else(|| {
+            next_id += 1;
+            next_id - 1
+        });
+        (key, id)
+    }).collect()
+}
+
+fn vehicle_ids(state: &AppState) -> HashMap<RunIdx, u64> {
+    fallback_ids(state.runs.iter().enumerate().map(|(run_idx, run)| (run_idx, run.vehicle_id.parse().ok())).collect())
+}
+
+fn job_ids(state: &AppState) -> HashMap<&str, u64> {
+    // Every job gets its own id, in plan order, whatever its uid looks like.
+    // The uid goes out as the step's description, which is how a solution finds its way back.
+    state.lane_indices()
+        .flat_map(|run_idx| state.lane(run_idx).into_iter().flatten())
+        .enumerate()
+        .map(|(i, job)| (job.uid.as_str(), i as u64 + 1))
+        .collect()
+}
+
+fn location(coords: Option<[f32; 2]>, index: Option<usize>) -> Location {
+    let [lon, lat] = coords.unwrap_or_default();
+    Location { lat, lon, matrix_idx: index }
+}
+
+fn to_job(task: &VroomJob, kind: &str, demand: &[f32]) -> Job {
+    let mut job = Job::new(
+        uid(kind, task.id),
+        UNASSIGNED_COLOR,
+        location(task.location, task.location_index),
+        to_minutes(task.service),
+    );
+    if let Some([earliest, latest]) = task.time_windows.first() {
+        job.window = TimeWindow::new(to_minutes(*earliest), to_minutes(*latest));
+    }
+    job.demand = Load(demand.to_vec());
+    job
+}
+
+fn first_non_empty<'a>(amounts: &[&'a Vec<f32>]) -> &'a [f32] {
+    amounts.iter().find(|amount| !amount.is_empty()).map_or(&[], |amount| amount.as_slice())
+}
+
+pub fn import_problem(json: &str, state: &mut AppState) -> Result<String, String> {
+    // Replaces the plan with one empty run per vehicle, and every task unassigned.
+    let problem: Problem = serde_json::from_str(json).map_err(|err| format!("Not a VROOM problem: {err}"))?;
+
+    let n_vehicles = problem.vehicles.len();
+    state.runs = problem.vehicles.iter().enumerate().map(|(i, vehicle)| {
+        let [start_time, end_time] = vehicle.time_window.map_or([0, 24 * 60], |window| window.map(to_minutes));
+        let start_depot = location(vehicle.start.or(vehicle.end), vehicle.start_index.or(vehicle.end_index));
+        let end_depot = location(vehicle.end.or(vehicle.start), vehicle.end_index.or(vehicle.start_index));
+        let mut run = Run::new(vehicle.id.to_string(), &get_cycled_color(i, n_vehicles), start_depot, start_time, end_time);
+        run.end_depot = end_depot;
+        run.capacity = (!vehicle.capacity.is_empty()).then(|| Load(vehicle.capacity.clone()));
+        run
+    }).collect();
+
+    // a job can both deliver and pick up, which the load on board keeps apart
+    state.unassigned = problem.jobs.iter()
+        .map(|task| {
+            let mut job = to_job(task, "job", first_non_empty(&[&task.delivery, &task.amount]));
+            job.pickup = Load(task.pickup.clone());
+            job
+        })
+        .collect();
+    for shipment in problem.shipments.iter() {
+        let mut pickup = to_job(&shipment.pickup, "pickup", &shipment.amount);
+        let mut delivery = to_job(&shipment.delivery, "delivery", &shipment.amount);
+        pickup.pair = Some(Pair { role: PairRole::Pickup, partner: delivery.uid.clone() });
+        delivery.pair = Some(Pair { role: PairRole::Delivery, partner: pickup.uid.clone() });
+        state.unassigned.extend([pickup, delivery]);
+    }
+
+    let matrix = problem.matrices.into_values().next()
+        .map(|matrix| (matrix.durations, matrix.distances))
+        .or((!problem.matrix.is_empty()).then_some((problem.matrix, vec![])));
+    if let Some((durations, distances)) = matrix {
+        // jobs and depots come with their row of the matrix
+        let matrix = TravelMatrix { durations: per_minute(durations), distances: per_km(distances), locations: vec![] };
+        state.travel_matrix = Some(Rc::new(matrix));
+        state.travel_model = TravelModel::Matrix;
+    } else {
+        // the last plan's matrix does not fit these locations, which are [lon, lat]
+        state.travel_matrix = None;
+        state.travel_model = TravelModel::GreatCircle;
+    }
+
+    state.reschedule();
+    Ok(format!(
+        "Loaded a VROOM problem with {} vehicles and {} tasks, now load its solution",
+        n_vehicles, state.unassigned.len(),
+    ))
+}
+
+pub fn import_solution(json: &str, state: &mut AppState) -> Result<String, String> {
+    // Puts the jobs of the current plan onto runs in the order of the solution's routes.
+    // Anything the solution does not route is left unassigned.
+    let solution: Solution = serde_json::from_str(json).map_err(|err| format!("Not a VROOM solution: {err}"))?;
+
+    // the same vehicle ids as an export, so that runs without a numeric id are found again
+    let run_indices: HashMap<u64, RunIdx> = vehicle_ids(state).into_iter().map(|(run_idx, id)| (id, run_idx)).collect();
+    let mut jobs = state.take_jobs();
+
+    let mut missing = vec![];
+    for route in solution.routes.iter() {
+        let Some(run) = run_indices.get(&route.vehicle).and_then(|run_idx| state.runs.get_mut(*run_idx)) else {
+            missing.push(format!("vehicle {}", route.vehicle));
+            continue
+        };
+        for step in route.steps.iter() {
+            let Some(id) = step.id else { continue };
+            if !["job", "pickup", "delivery"].contains(&step.kind.as_str()) { continue };
+            // our own exports name the job, VROOM's name it by kind and id
+            let described = step.description.clone().filter(|uid| jobs.contains_key(uid));
+            match jobs.remove(&described.unwrap_or_else(|| uid(&step.kind, id))) {
+                Some(mut job) => {
+                    job.color = run.color.clone();
+                    run.jobs.push(job);
+                }
+                None => missing.push(format!("{} {id}", step.kind)),
+            }
+        }
+    }
+
+    state.unassign(jobs);
+    state.reschedule();
+
+    let loaded = format!("Loaded {} routes", solution.routes.len());
+    match missing.is_empty() {
+        true => Ok(loaded),
+        false => Ok(format!("{loaded}, but the plan has no {}", missing.join(", "))),
+    }
+}
+
+fn coords(location: &Location) -> Option<[f32; 2]> {
+    Some([location.lon, location.lat])
+}
+
+fn export_route(vehicle: u64, run: &Run, state: &AppState, ids: &HashMap<&str, u64>) -> Route {
+    let travel = provider(&state.travel_model, state.travel_matrix.as_deref());
+    let profile = load_profile(&run.jobs);
+    let mut steps = vec![Step {
+        kind: "start".to_string(),
+        id: None,
+        description: None,
+        location: coords(&run.start_depot),
+        arrival: to_seconds(run.start_time),
+        duration: 0,
+        service: 0,
+        waiting_time: 0,
+        load: profile[0].0.clone(),
+    }];
+
+    let mut travelled = 0.0;
+    let mut here = &run.start_depot;
+    for (seq, job) in run.jobs.iter().enumerate() {
+        travelled += travel.travel_time(here, &job.location);
+        here = &job.location;
+        steps.push(Step {
+            kind: kind(job).to_string(),
+            id: Some(ids[job.uid.as_str()]),
+            description: Some(job.uid.clone()),
+            location: coords(&job.location),
+            arrival: to_seconds(job.times.arrival),
+            duration: (travelled * 60.0).round() as Seconds,
+            service: to_seconds(job.service_minutes),
+            waiting_time: to_seconds(job.times.start - job.times.arrival),
+            load: profile[seq + 1].0.clone(),
+        });
+    }
+    travelled += travel.travel_time(here, &run.end_depot);
+    steps.push(Step {
+        kind: "end".to_string(),
+        id: None,
+        description: None,
+        location: coords(&run.end_depot),
+        arrival: to_seconds(run.return_time),
+        duration: (travelled * 60.0).round() as Seconds,
+        service: 0,
+        waiting_time: 0,
+        load: profile.last().map(|load| load.0.clone()).unwrap_or_default(),
+    });
+
+    let duration = (travelled * 60.0).round() as Seconds;
+    let service = run.jobs.iter().map(|job| to_seconds(job.service_minutes)).sum();
+    let waiting_time = run.jobs.iter().map(|job| to_seconds(job.times.start - job.times.arrival)).sum();
+    Route {
+        vehicle,
+        steps,
+        cost: duration,
+        duration,
+        service,
+        waiting_time,
+    }
+}
+
+pub fn export_solution(state: &AppState) -> Result<String, String> {
+    let vehicles = vehicle_ids(state);
+    let ids = job_ids(state);
+    let routes: Vec<Route> = state.runs.iter().enumerate()
+        .filter(|(_, run)| !run.jobs.is_empty())
+        .map(|(run_idx, run)| export_route(vehicles[&run_idx], run, state, &ids))
+        .collect();
+    let unassigned: Vec<Unassigned> = state.lane(POOL_IDX).into_iter().flatten()
+        .map(|job| {
+            Unassigned { id: ids[job.uid.as_str()], kind: kind(job).to_string(), description: Some(job.uid.clone()) }
+        })
+        .collect();
+
+    let summary = Summary {
+        cost: routes.iter().map(|route| route.cost).sum(),
+        routes: routes.len(),
+        unassigned: unassigned.len(),
+        duration: routes.iter().map(|route| route.duration).sum(),
+        service: routes.iter().map(|route| route.service).sum(),
+        waiting_time: routes.iter().map(|route| route.waiting_time).sum(),
+    };
+    let solution = SolutionOut { code: 0, summary, unassigned, routes };
+    serde_json::to_string_pretty(&solution).map_err(|err| format!("Could not write the solution: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{new_state, Position, dnd::place_job};
+
+    const PROBLEM: &str = r#"{
+        "vehicles": [
+            { "id": 1, "start_index": 0, "end_index": 0, "capacity": [4], "time_window": [28800, 64800] },
+            { "id": 2, "start_index": 0, "end_index": 0, "capacity": [4] }
+        ],
+        "jobs": [
+            { "id": 1, "location_index": 1, "service": 300, "delivery": [1] },
+            { "id": 2, "location_index": 2, "service": 300, "delivery": [1] },
+            { "id": 3, "location_index": 3, "service": 300, "delivery": [1] }
+        ],
+        "shipments": [
+            { "pickup": { "id": 1, "location_index": 4 }, "delivery": { "id": 1, "location_index": 1 }, "amount": [2] }
+        ],
+        "matrices": { "car": { "durations": [
+            [0, 600, 600, 900, 900],
+            [600, 0, 300, 600, 600],
+            [600, 300, 0, 600, 600],
+            [900, 600, 600, 0, 300],
+            [900, 600, 600, 300, 0]
+        ] } }
+    }"#;
+
+    const SOLUTION: &str = r#"{
+        "code": 0,
+        "routes": [
+            { "vehicle": 1, "steps": [
+                { "type": "start" },
+                { "type": "job", "id": 2 },
+                { "type": "pickup", "id": 1 },
+                { "type": "delivery", "id": 1 },
+                { "type": "end" }
+            ] },
+            { "vehicle": 2, "steps": [
+                { "type": "start" },
+                { "type": "job", "id": 3 },
+                { "type": "end" }
+            ] }
+        ]
+    }"#;
+
+    fn job_order(state: &AppState) -> Vec<Vec<String>> {
+        state.lane_indices()
+            .map(|run_idx| state.lane(run_idx).unwrap().iter().map(|job| job.uid.clone()).collect())
+            .collect()
+    }
+
+    fn load(solution: &str) -> AppState {
+        let mut state = new_state();
+        import_problem(PROBLEM, &mut state).unwrap();
+        assert_eq!(import_solution(solution, &mut state), Ok("Loaded 2 routes".to_string()));
+        state
+    }
+
+    #[test]
+    fn round_trips_a_solution() {
+        let state = load(SOLUTION);
+        assert_eq!(job_order(&state)[..2], [vec!["2", "p1", "d1"], vec!["3"]]);
+        assert_eq!(state.unassigned.iter().map(|job| job.uid.as_str()).collect::<Vec<_>>(), ["1"]);
+
+        let exported = export_solution(&state).unwrap();
+        assert_eq!(job_order(&load(&exported)), job_order(&state));
+    }
+
+    #[test]
+    fn made_up_vehicle_ids_do_not_clash() {
+        let mut state = load(SOLUTION);
+        state.runs[0].vehicle_id = "van".to_string();
+        assert_eq!(vehicle_ids(&state)[&0], 3);
+    }
+
+    #[test]
+    fn exports_pairs_by_role_not_by_uid() {
+        // the demo plan's pool starts with pickup 12 and its delivery 13
+        let mut state = new_state();
+        place_job(&["12".to_string()], Position::new(1, 0), &mut state).unwrap();
+        state.runs[0].jobs[0].uid = "p7".to_string();
+        // run 2 already has a job 7
+        state.runs[0].jobs[1].uid = "07".to_string();
+
+        let exported = export_solution(&state).unwrap();
+        let solution: Value = serde_json::from_str(&exported).unwrap();
+        let steps = |vehicle: usize| -> Vec<(String, String)> {
+            solution["routes"][vehicle]["steps"].as_array().unwrap().iter()
+                .filter(|step| step["id"].is_u64())
+                .map(|step| (step["type"].as_str().unwrap().to_string(), step["description"].as_str().unwrap().to_string()))
+                .collect()
+        };
+        assert_eq!(&steps(0)[..2], [("job".into(), "p7".into()), ("job".into(), "07".into())]);
+        assert_eq!(&steps(1)[..2], [("pickup".into(), "12".into()), ("delivery".into(), "13".into())]);
+
+        let mut ids: Vec<u64> = job_ids(&state).into_values().collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), state.runs.iter().map(|run| run.jobs.len()).sum::<usize>() + state.unassigned.len());
+
+        let before = job_order(&state);
+        assert!(import_solution(&exported, &mut state).is_ok());
+        assert_eq!(job_order(&state), before);
+    }
+
+    #[test]
+    fn keeps_pickups_apart_from_deliveries() {
+        let problem = r#"{
+            "vehicles": [{ "id": 1, "start": [0, 0] }],
+            "jobs": [
+                { "id": 1, "location": [0, 1], "pickup": [3] },
+                { "id": 2, "location": [0, 2], "delivery": [2], "pickup": [1] }
+            ]
+        }"#;
+        let mut state = new_state();
+        import_problem(problem, &mut state).unwrap();
+        let jobs = &state.unassigned;
+        assert_eq!((&jobs[0].demand, &jobs[0].pickup), (&Load(vec![]), &Load(vec![3.0])));
+        assert_eq!((&jobs[1].demand, &jobs[1].pickup), (&Load(vec![2.0]), &Load(vec![1.0])));
+        // leaves with the delivery, and comes back with both pickups
+        assert_eq!(load_profile(jobs), [Load(vec![2.0]), Load(vec![5.0]), Load(vec![4.0])]);
+    }
+
+    #[test]
+    fn drops_the_last_matrix() {
+        let mut state = new_state();
+        import_problem(PROBLEM, &mut state).unwrap();
+        assert_eq!(state.travel_model, TravelModel::Matrix);
+        import_problem(r#"{ "vehicles": [{ "id": 1, "start": [0, 0] }] }"#, &mut state).unwrap();
+        assert_eq!((state.travel_model, state.travel_matrix), (TravelModel::GreatCircle, None));
+    }
+}
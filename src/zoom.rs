@@ -0,0 +1,50 @@
+use web_sys::{Element, WheelEvent};
+use yew::NodeRef;
+
+pub const MIN_PX_PER_MINUTE: f32 = 0.5;
+pub const MAX_PX_PER_MINUTE: f32 = 32.0;
+pub const ZOOM_STEP: f32 = 1.25;
+// how far the pan buttons move the timeline
+pub const PAN_MINUTES: f32 = 60.0;
+
+pub fn zoom(px_per_minute: f32, factor: f32) -> f32 {
+    (px_per_minute * factor).clamp(MIN_PX_PER_MINUTE, MAX_PX_PER_MINUTE)
+}
+
+pub fn wheel_factor(event: &WheelEvent) -> f32 {
+    // scrolling up (negative delta) zooms in
+    if event.delta_y() < 0.0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP }
+}
+
+pub fn pointer_offset(timeline_ref: &NodeRef, client_x: i32) -> Option<f64> {
+    // the pointer's horizontal offset from the left edge of the visible timeline.
+    let element = timeline_ref.cast::<Element>()?;
+    Some(client_x as f64 - element.get_bounding_client_rect().left())
+}
+
+pub fn scroll_to_keep_anchor(
+    timeline_ref: &NodeRef,
+    anchor_offset: Option<f64>,
+    old_px_per_minute: f32,
+    new_px_per_minute: f32,
+) -> Option<f64> {
+    // Returns the scroll position that keeps the time under `anchor_offset`
+    // (or the middle of the view) in the same place on screen after zooming.
+    let element = timeline_ref.cast::<Element>()?;
+    let offset = anchor_offset.unwrap_or(element.client_width() as f64 / 2.0);
+    let minutes = (element.scroll_left() as f64 + offset) / old_px_per_minute as f64;
+    Some(minutes * new_px_per_minute as f64 - offset)
+}
+
+pub fn set_scroll_left(timeline_ref: &NodeRef, scroll_left: f64) {
+    if let Some(element) = timeline_ref.cast::<Element>() {
+        element.set_scroll_left(scroll_left.round() as i32);
+    }
+}
+
+pub fn pan(timeline_ref: &NodeRef, minutes: f32, px_per_minute: f32) {
+    if let Some(element) = timeline_ref.cast::<Element>() {
+        let scroll_left = element.scroll_left() as f64 + (minutes * px_per_minute) as f64;
+        element.set_scroll_left(scroll_left.round() as i32);
+    }
+}